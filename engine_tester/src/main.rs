@@ -1,8 +1,79 @@
-use lumr::core::application::Application;
+use std::sync::Arc;
+
+use glam::{Mat4, Vec3};
+use lumr::core::{application::Application, context::Context, game::Game};
+use lumr::graphics::{camera::Camera, mesh::{Mesh, MeshData, MeshVertex}};
+use lumr::input::action_map::{ActionMap, Binding};
+use lumr::logger::info;
+use winit::event::VirtualKeyCode;
+
+#[derive(Default)]
+struct EngineTester {
+    triangle: Option<Arc<Mesh>>,
+    angle: f32,
+}
+
+impl Game for EngineTester {
+    fn on_start(&mut self, context: &mut Context) {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let triangle = MeshData::new(
+            vec![
+                MeshVertex::new([-0.5, -0.5, 0.0]).with_color(red),
+                MeshVertex::new([0.0, 0.5, 0.0]).with_color(red),
+                MeshVertex::new([0.5, -0.25, 0.0]).with_color(red),
+            ],
+            None,
+        );
+
+        match context.upload_mesh(&triangle) {
+            Ok(mesh) => self.triangle = Some(mesh),
+            Err(e) => lumr::logger::error!("failed to upload triangle: {e}"),
+        }
+
+        let camera = Camera::perspective(60f32.to_radians(), 0.1, 100.0)
+            .with_position(Vec3::new(0.0, 0.0, 2.0))
+            .look_at(Vec3::ZERO, Vec3::Y);
+        context.draw_list.set_camera(Some(camera));
+
+        info!("engine tester started, press Escape to quit");
+    }
+
+    fn on_update(&mut self, _context: &mut Context, dt: f32) {
+        self.angle += dt;
+    }
+
+    fn on_render(&mut self, context: &mut Context) {
+        if let Some(triangle) = &self.triangle {
+            context.draw_list.draw_with_transform(triangle, Mat4::from_rotation_y(self.angle));
+        }
+
+        if context.input.action_pressed("quit") {
+            context.exit();
+        }
+    }
+
+    fn on_shutdown(&mut self, _context: &mut Context) {
+        info!("engine tester shutting down");
+    }
+}
 
 fn main() {
     lumr::logger::init();
+
+    let mut action_map = ActionMap::new();
+    action_map.bind_action("quit", Binding::Key(VirtualKeyCode::Escape));
     
-    let application = Application::new();
-    application.run();
+    let application = Application::builder()
+        .with_title("lumr engine tester")
+        .with_size(1024, 768)
+        .with_action_map(action_map)
+        .build();
+
+    match application {
+        Ok(application) => application.run(EngineTester::default()),
+        Err(e) => {
+            lumr::logger::error!("failed to start application: {e}");
+            std::process::exit(1);
+        }
+    }
 }
@@ -1,107 +1,206 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
-use vulkano::{
-    sync::{self,future::FenceSignalFuture, FlushError, GpuFuture}, swapchain::{AcquireError, SwapchainPresentInfo, self},
-};
-use winit::dpi::PhysicalSize;
+use log::warn;
+use winit::{event::VirtualKeyCode, event_loop::ControlFlow};
 
+use crate::errors::Errors;
+use crate::input::{action_map::ActionMap, input_state::Input};
 use crate::graphics::{
-    window::GraphicsWindow,
+    window::{GraphicsWindow, WindowConfig, WindowLoopEvent},
     vulkan_instance::VulkanInstanse,
-    device_manager::DeviceManager, swapchain_manager::SwapchainManager, renderer::Renderer,
+    device_manager::DeviceManager, swapchain_manager::SwapchainManager, graphics_config::GraphicsConfig, renderer::Renderer,
+    frame_manager::FrameManager, capture::FrameCapture, gpu_resources::GpuResources,
+};
+
+use super::{
+    application_builder::ApplicationBuilder, context::Context, game::Game,
+    game_loop::{GameLoop, LoopConfig}, time::Time,
 };
 
 pub struct Application {
+    /// Kept alive so debug messages keep arriving until shutdown.
+    _vulkan_instanse: Arc<VulkanInstanse>,
     graphics_window: GraphicsWindow,
     device_manager: Arc<DeviceManager>,
     swapchain_manager: SwapchainManager,
     renderer: Renderer,
+    frame_manager: FrameManager,
+    frame_capture: FrameCapture,
+    screenshot_key: Option<VirtualKeyCode>,
+    game_loop: GameLoop,
+    action_map: ActionMap,
 }
 
 impl Application {
-    pub fn new() -> Application {
-        let vulkan_instanse = VulkanInstanse::new();
-        let graphics_window = GraphicsWindow::new(vulkan_instanse.instance.clone());
-        let device_manager = DeviceManager::new(&vulkan_instanse.instance, &graphics_window.surface);
-        let swapchain_manager = SwapchainManager::new(&graphics_window, device_manager.clone());
-        let renderer = Renderer::new(&device_manager, &swapchain_manager, &graphics_window.viewport);
-
-        Application { graphics_window, device_manager, swapchain_manager, renderer }
+    pub fn new() -> Result<Application, Errors> {
+        ApplicationBuilder::new().build()
     }
 
-    pub fn run(mut self) {
-        let mut recreate_swapchain = false;
-        let frames_in_flight = self.swapchain_manager.images.len();
-        let mut fences: Vec<Option<Arc<FenceSignalFuture<_>>>> = vec![None; frames_in_flight];
-        let mut previous_fence_i = 0;
+    pub fn builder() -> ApplicationBuilder {
+        ApplicationBuilder::new()
+    }
 
-        self.graphics_window.run_event_loop(
-            move |updated_dimensions: PhysicalSize<u32>, viewport, window_resized| {
-                if recreate_swapchain || window_resized {
-                    recreate_swapchain = false;
+    pub fn with_config(
+        window_config: &WindowConfig,
+        loop_config: LoopConfig,
+        action_map: ActionMap,
+        graphics_config: &GraphicsConfig,
+    ) -> Result<Application, Errors> {
+        let game_loop = GameLoop::new(loop_config)?;
+        let vulkan_instanse = VulkanInstanse::new(graphics_config.vulkan_debug)?;
+        let graphics_window = GraphicsWindow::new(vulkan_instanse.instance.clone(), window_config)?;
+        let device_manager = DeviceManager::new(&vulkan_instanse.instance, &graphics_window.surface, &graphics_config.device_selection)?;
+        let gpu_resources = GpuResources::new(&device_manager);
+        let swapchain_manager = SwapchainManager::new(&graphics_window, gpu_resources.clone(), graphics_config.swapchain)?;
+        let renderer = Renderer::new(&gpu_resources, graphics_config.pipeline_cache_dir.as_deref())?;
+        let frame_manager = FrameManager::new(&gpu_resources, graphics_config.frames_in_flight)?;
+        let frame_capture = FrameCapture::new(
+            gpu_resources,
+            frame_manager.frames_in_flight(),
+            graphics_config.screenshot_dir.clone(),
+        );
 
-                    self.swapchain_manager.recreate(updated_dimensions);
+        Ok(Application {
+            _vulkan_instanse: vulkan_instanse,
+            graphics_window,
+            device_manager,
+            swapchain_manager,
+            renderer,
+            frame_manager,
+            frame_capture,
+            screenshot_key: graphics_config.screenshot_key,
+            game_loop,
+            action_map,
+        })
+    }
 
-                    if window_resized {
-                        self.renderer.recreate_command_buffer(&self.device_manager, &self.swapchain_manager, viewport);
+    pub fn run(mut self, mut game: impl Game + 'static) {
+        let mut recreate_swapchain = false;
+        let mut context = Context::new(
+            self.graphics_window.window.clone(),
+            Time::new(self.game_loop.config().fixed_timestep),
+            Input::new(std::mem::take(&mut self.action_map)),
+            &self.renderer,
+            &self.swapchain_manager,
+        );
+        let mut resize_pending = false;
+
+        self.graphics_window.run_event_loop(
+            move |event, control_flow| {
+                let (updated_dimensions, window_resized) = match event {
+                    WindowLoopEvent::Started => {
+                        game.on_start(&mut context);
+                        Self::apply_exit_request(&context, control_flow);
+                        return Ok(());
+                    }
+                    WindowLoopEvent::Window(event) => {
+                        context.input.handle_window_event(event);
+                        game.on_event(&mut context, event);
+                        Self::apply_exit_request(&context, control_flow);
+                        return Ok(());
                     }
-                } 
-
-                let (image_i, suboptimal, acquire_future) =
-                match swapchain::acquire_next_image(self.swapchain_manager.swapchain.clone(), None) {
-                    Ok(r) => r,
-                    Err(AcquireError::OutOfDate) => {
-                        recreate_swapchain = true;
-                        return;
+                    WindowLoopEvent::Exiting => {
+                        game.on_shutdown(&mut context);
+                        if let Err(err) = self.frame_manager.wait_idle() {
+                            warn!("failed to wait for frames in flight: {err}");
+                        }
+                        if let Err(err) = self.frame_capture.finish() {
+                            warn!("failed to save captured frames: {err}");
+                        }
+                        if let Err(err) = self.renderer.save_pipeline_cache() {
+                            warn!("failed to save pipeline cache: {err}");
+                        }
+                        return Ok(());
+                    }
+                    WindowLoopEvent::Frame { dimensions, window_resized, .. } => {
+                        (dimensions, window_resized)
                     }
-                    Err(e) => panic!("failed to acquire next image: {e}"),
                 };
-        
-                if suboptimal {
-                    recreate_swapchain = true;
+
+                let now = Instant::now();
+                if !self.game_loop.frame_due(now) {
+                    // Keep the resize for the next frame that is actually drawn.
+                    resize_pending |= window_resized;
+                    *control_flow = self.game_loop.control_flow();
+                    return Ok(());
+                }
+                let window_resized = window_resized || std::mem::take(&mut resize_pending);
+
+                let steps = self.game_loop.begin_frame(now, &mut context.time);
+                *control_flow = self.game_loop.control_flow();
+
+                let dt = context.time.fixed_delta_seconds();
+                for _ in 0..steps {
+                    game.on_update(&mut context, dt);
                 }
-                
-                if let Some(image_fence) = &fences[image_i as usize] {
-                    image_fence.wait(None).unwrap();
+                context.draw_list.clear();
+                game.on_render(&mut context);
+                if self.screenshot_key.is_some_and(|key| context.input.key_pressed(key)) {
+                    context.take_screenshot(None);
                 }
+                context.input.end_frame();
+                Self::apply_exit_request(&context, control_flow);
 
-                let previous_future = match fences[previous_fence_i as usize].clone() {
-                    None => {
-                        let mut now = sync::now(self.device_manager.device.clone());
-                        now.cleanup_finished();
+                if let Some(swapchain_config) = context.take_swapchain_config_change() {
+                    self.swapchain_manager.set_config(swapchain_config);
+                    recreate_swapchain = true;
+                }
 
-                        now.boxed()
-                    }
-                    Some(fence) => fence.boxed(),
-                };
+                if std::mem::take(&mut recreate_swapchain) || window_resized {
+                    self.swapchain_manager.recreate(updated_dimensions)?;
+                    self.renderer.recreate_target_resources(&self.swapchain_manager);
+                    context.set_surface_format(self.swapchain_manager.surface_format);
+                }
 
-                let future = previous_future
-                    .join(acquire_future)
-                    .then_execute(
-                        self.device_manager.queue.clone(),
-                        self.renderer.command_buffers[image_i as usize].clone()
-                    )
-                    .unwrap()
-                    .then_swapchain_present(
-                        self.device_manager.queue.clone(),
-                        SwapchainPresentInfo::swapchain_image_index(self.swapchain_manager.swapchain.clone(), image_i),
-                    )
-                    .then_signal_fence_and_flush();
-
-                fences[image_i as usize] = match future {
-                    Ok(value) => Some(Arc::new(value)),
-                    Err(FlushError::OutOfDate) => {
-                        recreate_swapchain = true;
-                        None
-                    }
-                    Err(e) => {
-                        println!("failed to flush future: {e}");
-                        None
-                    }
+                let frame = self.frame_manager.acquire(&self.swapchain_manager.swapchain)?;
+                context.set_frame_stats(self.frame_manager.stats());
+                let Some(frame) = frame else {
+                    recreate_swapchain = self.frame_manager.take_recreate_request();
+                    return Ok(());
                 };
 
-                previous_fence_i = image_i;
+                // The frame that last used this slot has finished, so its capture can be read.
+                if let Err(err) = self.frame_capture.frame_finished(frame.index) {
+                    warn!("failed to save captured frame: {err}");
+                }
+                for request in context.take_capture_requests() {
+                    self.frame_capture.apply(request);
+                }
+                let capture_buffer = self.frame_capture.begin_frame(
+                    frame.index,
+                    self.swapchain_manager.capture_format(),
+                    self.swapchain_manager.swapchain.image_extent(),
+                )?;
+
+                self.renderer.reload_changed_shaders();
+                let frame_index = frame.index;
+                let command_buffer = self.renderer.record_frame(
+                    &self.swapchain_manager,
+                    self.frame_manager.resources(&frame),
+                    frame.image_index as usize,
+                    &context.draw_list,
+                    capture_buffer,
+                )?;
+                self.frame_manager.present(
+                    &self.device_manager,
+                    &self.swapchain_manager.swapchain,
+                    frame,
+                    command_buffer,
+                    self.renderer.take_submitted(),
+                )?;
+                if !self.frame_manager.is_submitted(frame_index) {
+                    self.frame_capture.cancel_frame(frame_index);
+                }
+                recreate_swapchain = self.frame_manager.take_recreate_request();
+
+                Ok(())
             },
         );
     }
-}
\ No newline at end of file
+
+    fn apply_exit_request(context: &Context, control_flow: &mut ControlFlow) {
+        if context.exit_requested() {
+            *control_flow = ControlFlow::Exit;
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::{path::PathBuf, time::Duration};
+
+use winit::event::VirtualKeyCode;
+
+use crate::errors::Errors;
+use crate::graphics::{
+    device_selection::DeviceSelection, graphics_config::GraphicsConfig, swapchain_manager::SwapchainConfig,
+    window::WindowConfig,
+};
+use crate::input::action_map::ActionMap;
+
+use super::{application::Application, game_loop::LoopConfig};
+
+/// Configures and creates an `Application`.
+#[derive(Default)]
+pub struct ApplicationBuilder {
+    window_config: WindowConfig,
+    loop_config: LoopConfig,
+    action_map: ActionMap,
+    graphics_config: GraphicsConfig,
+}
+
+impl ApplicationBuilder {
+    pub fn new() -> ApplicationBuilder {
+        ApplicationBuilder::default()
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> ApplicationBuilder {
+        self.window_config.title = title.into();
+        self
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> ApplicationBuilder {
+        self.window_config.width = width;
+        self.window_config.height = height;
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> ApplicationBuilder {
+        self.window_config.resizable = resizable;
+        self
+    }
+
+    /// Sets the length of one simulation step passed to `Game::on_update`;
+    /// `build` fails if it is zero.
+    pub fn with_fixed_timestep(mut self, fixed_timestep: Duration) -> ApplicationBuilder {
+        self.loop_config.fixed_timestep = fixed_timestep;
+        self
+    }
+
+    /// Caps the number of rendered frames per second; `None` renders as fast as possible.
+    pub fn with_max_fps(mut self, max_fps: Option<u32>) -> ApplicationBuilder {
+        self.loop_config.max_fps = max_fps;
+        self
+    }
+
+    /// Sets the action and axis bindings queried through `Context::input`.
+    pub fn with_action_map(mut self, action_map: ActionMap) -> ApplicationBuilder {
+        self.action_map = action_map;
+        self
+    }
+
+    /// Sets where compiled pipelines are kept between launches; `None` keeps
+    /// them in memory only. Defaults to a `lumr` folder in the user cache directory.
+    pub fn with_pipeline_cache_dir(mut self, directory: Option<PathBuf>) -> ApplicationBuilder {
+        self.graphics_config.pipeline_cache_dir = directory;
+        self
+    }
+
+    /// Enables the Khronos validation layer and routes its messages to the
+    /// log. Setting `LUMR_VULKAN_DEBUG=1` does the same without a rebuild.
+    pub fn with_vulkan_debug(mut self, vulkan_debug: bool) -> ApplicationBuilder {
+        self.graphics_config.vulkan_debug = vulkan_debug;
+        self
+    }
+
+    /// Sets the present mode and swapchain image count. Can be changed later
+    /// through `Context::set_swapchain_config`.
+    pub fn with_swapchain_config(mut self, swapchain_config: SwapchainConfig) -> ApplicationBuilder {
+        self.graphics_config.swapchain = swapchain_config;
+        self
+    }
+
+    pub fn with_vsync(mut self, vsync: bool) -> ApplicationBuilder {
+        self.graphics_config.swapchain = self.graphics_config.swapchain.with_vsync(vsync);
+        self
+    }
+
+    /// Enables multisample anti-aliasing with 2, 4 or 8 samples per pixel,
+    /// lowered to what the device supports.
+    pub fn with_msaa_samples(mut self, msaa_samples: u32) -> ApplicationBuilder {
+        self.graphics_config.swapchain = self.graphics_config.swapchain.with_msaa_samples(msaa_samples);
+        self
+    }
+
+    /// Sets how many frames the CPU may record before waiting for the GPU.
+    /// More frames smooth out stalls at the cost of latency. Defaults to 2.
+    pub fn with_frames_in_flight(mut self, frames_in_flight: usize) -> ApplicationBuilder {
+        self.graphics_config.frames_in_flight = frames_in_flight;
+        self
+    }
+
+    /// Sets which GPU to use and what it must support. Setting `LUMR_DEVICE`
+    /// forces a device without a rebuild.
+    pub fn with_device_selection(mut self, device_selection: DeviceSelection) -> ApplicationBuilder {
+        self.graphics_config.device_selection = device_selection;
+        self
+    }
+
+    /// Sets the key that saves a screenshot, F12 by default; `None` disables it.
+    pub fn with_screenshot_key(mut self, key: Option<VirtualKeyCode>) -> ApplicationBuilder {
+        self.graphics_config.screenshot_key = key;
+        self
+    }
+
+    /// Sets where screenshots taken with the hotkey or without a path are saved.
+    pub fn with_screenshot_dir(mut self, directory: impl Into<PathBuf>) -> ApplicationBuilder {
+        self.graphics_config.screenshot_dir = directory.into();
+        self
+    }
+
+    /// Replaces all graphics options at once.
+    pub fn with_graphics_config(mut self, graphics_config: GraphicsConfig) -> ApplicationBuilder {
+        self.graphics_config = graphics_config;
+        self
+    }
+
+    pub fn build(self) -> Result<Application, Errors> {
+        Application::with_config(
+            &self.window_config,
+            self.loop_config,
+            self.action_map,
+            &self.graphics_config,
+        )
+    }
+}
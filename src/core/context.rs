@@ -0,0 +1,230 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use image::RgbaImage;
+use vulkano::device::Device;
+use winit::window::Window;
+
+use crate::errors::Errors;
+use crate::graphics::{
+    capture::CaptureRequest, draw_list::DrawList, frame_manager::FrameStats, gpu_resources::{GpuResources, HeapStats}, mesh::{Mesh, MeshData},
+    material::{MaterialBuilder, StandardShaders}, renderer::Renderer, swapchain_manager::{SurfaceFormat, SwapchainConfig, SwapchainManager}, shader::{Shader, ShaderStage, ShaderWatcher},
+    texture::{Texture, TextureOptions}, upload_scheduler::UploadScheduler,
+};
+use crate::input::input_state::Input;
+
+use super::time::Time;
+
+/// Engine state handed to every `Game` hook.
+pub struct Context {
+    pub window: Arc<Window>,
+    pub time: Time,
+    pub input: Input,
+    /// Filled by `Game::on_render`; cleared before every frame.
+    pub draw_list: DrawList,
+    gpu_resources: Arc<GpuResources>,
+    upload_scheduler: Arc<UploadScheduler>,
+    standard_shaders: Arc<StandardShaders>,
+    shader_watcher: Arc<ShaderWatcher>,
+    exit_requested: bool,
+    swapchain_config: SwapchainConfig,
+    swapchain_config_changed: bool,
+    surface_format: SurfaceFormat,
+    frame_stats: FrameStats,
+    capture_requests: Vec<CaptureRequest>,
+    recording_frames: bool,
+}
+
+impl Context {
+    /// Shares `renderer`'s GPU resources and shaders, so resources created
+    /// here can be drawn by it.
+    pub fn new(
+        window: Arc<Window>,
+        time: Time,
+        input: Input,
+        renderer: &Renderer,
+        swapchain_manager: &SwapchainManager,
+    ) -> Context {
+        Context {
+            window,
+            time,
+            input,
+            draw_list: DrawList::new(),
+            gpu_resources: renderer.gpu_resources().clone(),
+            upload_scheduler: renderer.upload_scheduler().clone(),
+            standard_shaders: renderer.standard_shaders().clone(),
+            shader_watcher: renderer.shader_watcher().clone(),
+            exit_requested: false,
+            swapchain_config: swapchain_manager.config(),
+            swapchain_config_changed: false,
+            surface_format: swapchain_manager.surface_format,
+            frame_stats: FrameStats::default(),
+            capture_requests: Vec::new(),
+            recording_frames: false,
+        }
+    }
+
+    /// Uploads mesh geometry to the GPU so it can be added to `draw_list`.
+    pub fn upload_mesh(&self, data: &MeshData) -> Result<Arc<Mesh>, Errors> {
+        Mesh::new(&self.gpu_resources, data)
+    }
+
+    /// Decodes a PNG, JPEG, BMP or TGA file and uploads it as a texture.
+    pub fn load_texture(&self, path: impl AsRef<Path>, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        Texture::from_file(&self.gpu_resources, path, options)
+    }
+
+    pub fn upload_texture(&self, image: &RgbaImage, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        Texture::new(&self.gpu_resources, image, options)
+    }
+
+    /// Like `upload_mesh`, but returns without waiting for the copy; the mesh
+    /// can still be drawn right away. Meant for large meshes loaded mid-game.
+    pub fn stream_mesh(&self, data: &MeshData) -> Result<Arc<Mesh>, Errors> {
+        self.upload_scheduler.upload_mesh(data)
+    }
+
+    /// Like `load_texture`, but returns without waiting for the upload.
+    pub fn stream_texture(&self, path: impl AsRef<Path>, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        self.upload_scheduler.load_texture(path, options)
+    }
+
+    /// Uploads assets without waiting, on the transfer queue if there is one.
+    pub fn upload_scheduler(&self) -> &Arc<UploadScheduler> {
+        &self.upload_scheduler
+    }
+
+    /// Starts a material drawn with the built-in vertex colored shaders.
+    pub fn material_builder(&self) -> MaterialBuilder {
+        self.material_builder_with_shaders(self.standard_shaders.vertex.clone(), self.standard_shaders.color.clone())
+    }
+
+    /// Starts a material multiplying vertex colors with `texture`.
+    pub fn textured_material_builder(&self, texture: &Arc<Texture>) -> MaterialBuilder {
+        self.material_builder_with_shaders(self.standard_shaders.vertex.clone(), self.standard_shaders.textured.clone())
+            .with_texture(texture)
+    }
+
+    /// Loads a GLSL or SPIR-V shader file. The application reloads it when the
+    /// file changes and keeps the previous version if that fails.
+    pub fn load_shader(&self, path: impl AsRef<Path>, stage: ShaderStage) -> Result<Arc<Shader>, Errors> {
+        self.shader_watcher.load(path, stage)
+    }
+
+    /// Starts a material with custom shaders, e.g. from `load_shader`.
+    pub fn material_builder_with_shaders(
+        &self,
+        vertex_shader: Arc<Shader>,
+        fragment_shader: Arc<Shader>,
+    ) -> MaterialBuilder {
+        MaterialBuilder::new(self.gpu_resources.clone(), vertex_shader, fragment_shader)
+    }
+
+    pub fn device(&self) -> &Arc<Device> {
+        self.gpu_resources.device()
+    }
+
+    /// Allocators and upload helpers for creating custom GPU resources.
+    pub fn gpu_resources(&self) -> &Arc<GpuResources> {
+        &self.gpu_resources
+    }
+
+    /// Memory allocated per device heap by resources that are still alive.
+    pub fn memory_stats(&self) -> Vec<HeapStats> {
+        self.gpu_resources.memory_stats()
+    }
+
+    pub fn swapchain_config(&self) -> SwapchainConfig {
+        self.swapchain_config
+    }
+
+    /// Recreates the swapchain with `config` before the next frame.
+    pub fn set_swapchain_config(&mut self, config: SwapchainConfig) {
+        if config != self.swapchain_config {
+            self.swapchain_config = config;
+            self.swapchain_config_changed = true;
+        }
+    }
+
+    /// Format and color space the window is presented in. Shaders writing to
+    /// a non-sRGB format in the sRGB color space have to encode gamma.
+    pub fn surface_format(&self) -> SurfaceFormat {
+        self.surface_format
+    }
+
+    pub(crate) fn set_surface_format(&mut self, surface_format: SurfaceFormat) {
+        self.surface_format = surface_format;
+    }
+
+    /// How long the CPU waited for the GPU and the swapchain so far.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    pub(crate) fn set_frame_stats(&mut self, frame_stats: FrameStats) {
+        self.frame_stats = frame_stats;
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.swapchain_config.vsync()
+    }
+
+    /// Switches between `Fifo` and `Immediate` presentation before the next frame.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.set_swapchain_config(self.swapchain_config.with_vsync(vsync));
+    }
+
+    pub fn msaa_samples(&self) -> u32 {
+        self.swapchain_config.msaa_samples
+    }
+
+    /// Changes multisample anti-aliasing before the next frame. 1 turns it off;
+    /// counts the device lacks fall back to the next lower one.
+    pub fn set_msaa_samples(&mut self, msaa_samples: u32) {
+        self.set_swapchain_config(self.swapchain_config.with_msaa_samples(msaa_samples));
+    }
+
+    /// The config set since the last call, if it changed.
+    pub fn take_swapchain_config_change(&mut self) -> Option<SwapchainConfig> {
+        std::mem::take(&mut self.swapchain_config_changed).then_some(self.swapchain_config)
+    }
+
+    /// Saves the next presented frame as a PNG file at `path`, or in the
+    /// screenshot directory without one.
+    pub fn take_screenshot(&mut self, path: Option<PathBuf>) {
+        self.capture_requests.push(CaptureRequest::Screenshot(path));
+    }
+
+    /// Saves every presented frame as a numbered PNG file in `directory`
+    /// until `stop_frame_sequence`, e.g. for turning into a video.
+    pub fn start_frame_sequence(&mut self, directory: impl Into<PathBuf>) {
+        self.capture_requests.push(CaptureRequest::StartSequence(directory.into()));
+        self.recording_frames = true;
+    }
+
+    pub fn stop_frame_sequence(&mut self) {
+        if self.recording_frames {
+            self.capture_requests.push(CaptureRequest::StopSequence);
+            self.recording_frames = false;
+        }
+    }
+
+    pub fn recording_frames(&self) -> bool {
+        self.recording_frames
+    }
+
+    pub(crate) fn take_capture_requests(&mut self) -> Vec<CaptureRequest> {
+        std::mem::take(&mut self.capture_requests)
+    }
+
+    /// Asks the application to stop after the current hook returns.
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+}
@@ -0,0 +1,24 @@
+use winit::event::WindowEvent;
+
+use super::context::Context;
+
+/// Game logic driven by an `Application`. Every hook has an empty default,
+/// so a game only implements the ones it needs.
+pub trait Game {
+    /// Called once, before the first frame.
+    fn on_start(&mut self, _context: &mut Context) {}
+
+    /// Called zero or more times per frame, once per fixed simulation step;
+    /// `dt` is the step length in seconds.
+    fn on_update(&mut self, _context: &mut Context, _dt: f32) {}
+
+    /// Called once per rendered frame, after the frame's updates. Use
+    /// `context.time.alpha()` to interpolate between simulation steps.
+    fn on_render(&mut self, _context: &mut Context) {}
+
+    /// Called for every event the window receives.
+    fn on_event(&mut self, _context: &mut Context, _event: &WindowEvent) {}
+
+    /// Called once when the application is about to exit.
+    fn on_shutdown(&mut self, _context: &mut Context) {}
+}
@@ -0,0 +1,177 @@
+use std::time::{Duration, Instant};
+
+use winit::event_loop::ControlFlow;
+
+use crate::errors::Errors;
+
+use super::time::Time;
+
+/// Largest interpolation factor, so `Time::alpha` stays below 1 even when a
+/// whole step is left over or the division rounds up.
+const MAX_ALPHA: f32 = 1.0 - f32::EPSILON;
+
+#[derive(Clone, Debug)]
+pub struct LoopConfig {
+    /// Length of one `Game::on_update` step. Must not be zero.
+    pub fixed_timestep: Duration,
+    /// Upper bound on rendered frames per second, or `None` to render as fast as possible.
+    pub max_fps: Option<u32>,
+    /// Simulation steps run at most per frame; time beyond that is dropped so
+    /// a slow frame cannot make the next one even slower.
+    pub max_updates_per_frame: u32,
+}
+
+impl Default for LoopConfig {
+    fn default() -> Self {
+        LoopConfig {
+            fixed_timestep: Duration::from_secs_f64(1.0 / 60.0),
+            max_fps: None,
+            max_updates_per_frame: 8,
+        }
+    }
+}
+
+impl LoopConfig {
+    pub fn validate(&self) -> Result<(), Errors> {
+        if self.fixed_timestep.is_zero() {
+            return Err(Errors::Config("fixed timestep must be longer than zero".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits wall-clock time into fixed simulation steps and paces rendering.
+pub struct GameLoop {
+    config: LoopConfig,
+    last_frame: Option<Instant>,
+    next_frame: Option<Instant>,
+    accumulator: Duration,
+}
+
+impl GameLoop {
+    /// Fails if `config` doesn't pass `LoopConfig::validate`.
+    pub fn new(config: LoopConfig) -> Result<GameLoop, Errors> {
+        config.validate()?;
+
+        Ok(GameLoop { config, last_frame: None, next_frame: None, accumulator: Duration::ZERO })
+    }
+
+    pub fn config(&self) -> &LoopConfig {
+        &self.config
+    }
+
+    /// Returns `false` while the frame-rate cap says the next frame should wait.
+    pub fn frame_due(&self, now: Instant) -> bool {
+        self.next_frame.is_none_or(|next_frame| now >= next_frame)
+    }
+
+    /// Starts a frame at `now`, updating `time`, and returns how many fixed
+    /// simulation steps should run before rendering it.
+    pub fn begin_frame(&mut self, now: Instant, time: &mut Time) -> u32 {
+        let delta = self.last_frame.map_or(Duration::ZERO, |last_frame| now - last_frame);
+        self.last_frame = Some(now);
+        time.advance(delta);
+
+        let fixed_timestep = self.config.fixed_timestep;
+        self.accumulator += delta;
+
+        let mut steps = 0;
+        while self.accumulator >= fixed_timestep && steps < self.config.max_updates_per_frame {
+            self.accumulator -= fixed_timestep;
+            steps += 1;
+        }
+        if steps == self.config.max_updates_per_frame {
+            self.accumulator = self.accumulator.min(fixed_timestep);
+        }
+
+        time.set_alpha((self.accumulator.as_secs_f32() / fixed_timestep.as_secs_f32()).min(MAX_ALPHA));
+
+        if let Some(max_fps) = self.config.max_fps.filter(|max_fps| *max_fps > 0) {
+            let frame_time = Duration::from_secs_f64(1.0 / max_fps as f64);
+            let next_frame = self.next_frame.unwrap_or(now) + frame_time;
+            // After a stall, render the next frame right away instead of
+            // rushing through every missed one.
+            self.next_frame = Some(next_frame.max(now));
+        }
+
+        steps
+    }
+
+    /// How the event loop should wait for the next frame.
+    pub fn control_flow(&self) -> ControlFlow {
+        match self.next_frame {
+            Some(next_frame) => ControlFlow::WaitUntil(next_frame),
+            None => ControlFlow::Poll,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_loop(fixed_timestep_ms: u64, max_fps: Option<u32>) -> (GameLoop, Time) {
+        let config = LoopConfig {
+            fixed_timestep: Duration::from_millis(fixed_timestep_ms),
+            max_fps,
+            max_updates_per_frame: 4,
+        };
+        let time = Time::new(config.fixed_timestep);
+
+        (GameLoop::new(config).unwrap(), time)
+    }
+
+    #[test]
+    fn accumulates_time_into_fixed_steps() {
+        let (mut game_loop, mut time) = game_loop(10, None);
+        let start = Instant::now();
+
+        assert_eq!(game_loop.begin_frame(start, &mut time), 0);
+        assert_eq!(game_loop.begin_frame(start + Duration::from_millis(25), &mut time), 2);
+        assert!((time.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(game_loop.begin_frame(start + Duration::from_millis(30), &mut time), 1);
+        assert_eq!(time.frame_count(), 3);
+        assert_eq!(time.elapsed(), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn drops_time_beyond_max_updates() {
+        let (mut game_loop, mut time) = game_loop(10, None);
+        let start = Instant::now();
+
+        game_loop.begin_frame(start, &mut time);
+        assert_eq!(game_loop.begin_frame(start + Duration::from_secs(1), &mut time), 4);
+        assert_eq!(game_loop.begin_frame(start + Duration::from_millis(1005), &mut time), 1);
+    }
+
+    #[test]
+    fn alpha_stays_below_one() {
+        let (mut game_loop, mut time) = game_loop(10, None);
+        let start = Instant::now();
+
+        game_loop.begin_frame(start, &mut time);
+        game_loop.begin_frame(start + Duration::from_secs(1), &mut time);
+        assert!(time.alpha() < 1.0);
+    }
+
+    #[test]
+    fn zero_timestep_is_rejected() {
+        let config = LoopConfig { fixed_timestep: Duration::ZERO, ..LoopConfig::default() };
+
+        assert!(matches!(config.validate(), Err(Errors::Config(_))));
+        assert!(matches!(GameLoop::new(config), Err(Errors::Config(_))));
+        assert!(LoopConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn frame_cap_delays_next_frame() {
+        let (mut game_loop, mut time) = game_loop(10, Some(100));
+        let start = Instant::now();
+
+        game_loop.begin_frame(start, &mut time);
+        assert!(!game_loop.frame_due(start + Duration::from_millis(5)));
+        assert!(game_loop.frame_due(start + Duration::from_millis(10)));
+        assert_eq!(game_loop.control_flow(), ControlFlow::WaitUntil(start + Duration::from_millis(10)));
+    }
+}
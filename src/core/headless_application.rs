@@ -0,0 +1,115 @@
+use std::{path::Path, sync::Arc};
+
+use image::RgbaImage;
+use vulkano::sync::{self, GpuFuture};
+
+use crate::errors::Errors;
+use crate::graphics::{
+    vulkan_instance::VulkanInstanse,
+    device_manager::DeviceManager, offscreen_target::OffscreenTarget, renderer::Renderer,
+    draw_list::DrawList, mesh::{Mesh, MeshData}, texture::{Texture, TextureOptions},
+    material::MaterialBuilder, shader::{Shader, ShaderStage}, frame_manager::{self, FrameResources},
+    device_selection::DeviceSelection, gpu_resources::GpuResources,
+};
+
+/// Runs the renderer without a window or surface, drawing into an
+/// `OffscreenTarget` whose pixels can be read back after every frame.
+pub struct HeadlessApplication {
+    _vulkan_instanse: Arc<VulkanInstanse>,
+    device_manager: Arc<DeviceManager>,
+    offscreen_target: OffscreenTarget,
+    renderer: Renderer,
+    /// Frames are rendered one at a time, so a single set is enough.
+    frame_resources: FrameResources,
+    draw_list: DrawList,
+}
+
+impl HeadlessApplication {
+    pub fn new(width: u32, height: u32) -> Result<HeadlessApplication, Errors> {
+        let vulkan_instanse = VulkanInstanse::new_headless(false)?;
+        let device_manager = DeviceManager::new_headless(&vulkan_instanse.instance, &DeviceSelection::default())?;
+        let gpu_resources = GpuResources::new(&device_manager);
+        let offscreen_target = OffscreenTarget::new(&gpu_resources, width, height)?;
+        let renderer = Renderer::new(&gpu_resources, None)?;
+        let upload_buffer = gpu_resources.upload_ring(frame_manager::UPLOAD_BUFFER_SIZE, 1)?.remove(0);
+        let frame_resources = FrameResources::new(&gpu_resources, upload_buffer);
+
+        Ok(HeadlessApplication {
+            _vulkan_instanse: vulkan_instanse,
+            device_manager,
+            offscreen_target,
+            renderer,
+            frame_resources,
+            draw_list: DrawList::new(),
+        })
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.offscreen_target.dimensions()
+    }
+
+    pub fn upload_mesh(&self, data: &MeshData) -> Result<Arc<Mesh>, Errors> {
+        self.renderer.upload_mesh(data)
+    }
+
+    pub fn load_texture(&self, path: impl AsRef<Path>, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        self.renderer.load_texture(path, options)
+    }
+
+    pub fn upload_texture(&self, image: &RgbaImage, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        self.renderer.upload_texture(image, options)
+    }
+
+    pub fn material_builder(&self) -> MaterialBuilder {
+        self.renderer.material_builder()
+    }
+
+    pub fn textured_material_builder(&self, texture: &Arc<Texture>) -> MaterialBuilder {
+        self.renderer.textured_material_builder(texture)
+    }
+
+    pub fn material_builder_with_shaders(&self, vertex_shader: Arc<Shader>, fragment_shader: Arc<Shader>) -> MaterialBuilder {
+        MaterialBuilder::new(self.renderer.gpu_resources().clone(), vertex_shader, fragment_shader)
+    }
+
+    pub fn load_shader(&self, path: impl AsRef<Path>, stage: ShaderStage) -> Result<Arc<Shader>, Errors> {
+        self.renderer.load_shader(path, stage)
+    }
+
+    /// Reloads shader files changed since the last call. `render_frame` doesn't
+    /// do this on its own, so tests control when reloads happen.
+    pub fn reload_changed_shaders(&mut self) {
+        self.renderer.reload_changed_shaders();
+    }
+
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    /// What `render_frame` draws. Unlike `Application`, it is kept between frames.
+    pub fn draw_list_mut(&mut self) -> &mut DrawList {
+        &mut self.draw_list
+    }
+
+    /// Renders one frame and returns it as tightly packed RGBA8 rows.
+    pub fn render_frame(&mut self) -> Result<Vec<u8>, Errors> {
+        // The previous frame was waited for, so its uploads can be overwritten.
+        self.frame_resources.upload_buffer.reset();
+        let command_buffer = self.renderer.record_frame(
+            &self.offscreen_target,
+            &self.frame_resources,
+            0,
+            &self.draw_list,
+            None,
+        )?;
+
+        let submitted = self.renderer.take_submitted();
+        submitted
+            .unwrap_or_else(|| sync::now(self.device_manager.device.clone()).boxed())
+            .then_execute(self.device_manager.queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+
+        self.offscreen_target.read_back()
+    }
+}
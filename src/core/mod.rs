@@ -1 +1,7 @@
-pub mod application;
\ No newline at end of file
+pub mod application;
+pub mod application_builder;
+pub mod context;
+pub mod game;
+pub mod game_loop;
+pub mod headless_application;
+pub mod time;
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+/// Weight of the newest frame in the smoothed frame rate.
+const FPS_SMOOTHING: f32 = 0.1;
+
+/// Frame timing exposed to gameplay code through the `Context`.
+#[derive(Clone, Debug)]
+pub struct Time {
+    delta: Duration,
+    elapsed: Duration,
+    frame_count: u64,
+    fps: f32,
+    fixed_delta: Duration,
+    alpha: f32,
+}
+
+impl Time {
+    pub fn new(fixed_delta: Duration) -> Time {
+        Time {
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            frame_count: 0,
+            fps: 0.0,
+            fixed_delta,
+            alpha: 0.0,
+        }
+    }
+
+    /// Time between the last two rendered frames.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Time since the first frame.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// Number of frames rendered so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Frame rate smoothed over recent frames.
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Length of one simulation step.
+    pub fn fixed_delta(&self) -> Duration {
+        self.fixed_delta
+    }
+
+    pub fn fixed_delta_seconds(&self) -> f32 {
+        self.fixed_delta.as_secs_f32()
+    }
+
+    /// How far the current frame lies between the last simulation step and
+    /// the next one, in `[0, 1)`. Use it to interpolate rendered state.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    pub(crate) fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.elapsed += delta;
+        self.frame_count += 1;
+
+        if !delta.is_zero() {
+            let fps = 1.0 / delta.as_secs_f32();
+            self.fps = if self.fps == 0.0 {
+                fps
+            } else {
+                self.fps + (fps - self.fps) * FPS_SMOOTHING
+            };
+        }
+    }
+
+    pub(crate) fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
@@ -1,7 +1,288 @@
 use thiserror::Error;
+use vulkano::{
+    buffer::BufferError,
+    command_buffer::{
+        BuildError, CommandBufferBeginError, CommandBufferExecError, CopyError,
+        PipelineExecutionError, RenderPassError,
+    },
+    descriptor_set::DescriptorSetCreationError,
+    device::{physical::PhysicalDeviceError, DeviceCreationError},
+    image::{immutable::ImmutableImageCreationError, view::ImageViewCreationError, ImageError},
+    instance::{debug::DebugUtilsMessengerCreationError, InstanceCreationError},
+    memory::allocator::AllocationCreationError,
+    pipeline::{compute::ComputePipelineCreationError, graphics::GraphicsPipelineCreationError},
+    render_pass::{FramebufferCreationError, RenderPassCreationError},
+    sampler::SamplerCreationError,
+    shader::ShaderCreationError,
+    swapchain::{AcquireError, SurfaceCreationError, SwapchainCreationError},
+    sync::FlushError,
+    LoadingError, OomError, VulkanError,
+};
 
 #[derive(Error,Debug)]
 pub enum Errors {
     #[error("test error")]
     TestError,
-}
\ No newline at end of file
+    #[error("no local Vulkan library/DLL: {0}")]
+    VulkanLibraryMissing(LoadingError),
+    #[error("failed to create Vulkan instance: {0}")]
+    InstanceCreation(InstanceCreationError),
+    #[error("no suitable physical device available:\n{0}")]
+    NoSuitableDevice(String),
+    #[error("failed to create logical device: {0}")]
+    DeviceCreation(DeviceCreationError),
+    #[error("failed to create window: {0}")]
+    WindowCreation(String),
+    #[error("surface error: {0}")]
+    Surface(String),
+    #[error("swapchain error: {0}")]
+    Swapchain(String),
+    #[error("failed to load shader: {0}")]
+    ShaderLoad(String),
+    #[error("out of memory: {0}")]
+    OutOfMemory(OomError),
+    #[error("the graphics device was lost")]
+    DeviceLost,
+    #[error("graphics error: {0}")]
+    Graphics(String),
+    #[error("I/O error: {0}")]
+    Io(std::io::Error),
+    #[error("invalid config: {0}")]
+    Config(String),
+    #[error("failed to decode image: {0}")]
+    ImageDecode(String),
+}
+
+impl From<OomError> for Errors {
+    fn from(err: OomError) -> Self {
+        Errors::OutOfMemory(err)
+    }
+}
+
+impl From<VulkanError> for Errors {
+    fn from(err: VulkanError) -> Self {
+        match err {
+            VulkanError::OutOfHostMemory | VulkanError::OutOfDeviceMemory => {
+                Errors::OutOfMemory(err.into())
+            }
+            VulkanError::DeviceLost => Errors::DeviceLost,
+            VulkanError::SurfaceLost => Errors::Surface(err.to_string()),
+            err => Errors::Graphics(err.to_string()),
+        }
+    }
+}
+
+impl From<LoadingError> for Errors {
+    fn from(err: LoadingError) -> Self {
+        match err {
+            LoadingError::OomError(err) => Errors::OutOfMemory(err),
+            err => Errors::VulkanLibraryMissing(err),
+        }
+    }
+}
+
+impl From<InstanceCreationError> for Errors {
+    fn from(err: InstanceCreationError) -> Self {
+        match err {
+            InstanceCreationError::OomError(err) => Errors::OutOfMemory(err),
+            err => Errors::InstanceCreation(err),
+        }
+    }
+}
+
+impl From<DeviceCreationError> for Errors {
+    fn from(err: DeviceCreationError) -> Self {
+        match err {
+            DeviceCreationError::OutOfHostMemory => Errors::OutOfMemory(OomError::OutOfHostMemory),
+            DeviceCreationError::OutOfDeviceMemory => Errors::OutOfMemory(OomError::OutOfDeviceMemory),
+            DeviceCreationError::DeviceLost => Errors::DeviceLost,
+            err => Errors::DeviceCreation(err),
+        }
+    }
+}
+
+impl From<PhysicalDeviceError> for Errors {
+    fn from(err: PhysicalDeviceError) -> Self {
+        match err {
+            PhysicalDeviceError::VulkanError(err) => err.into(),
+            err => Errors::Surface(err.to_string()),
+        }
+    }
+}
+
+impl From<vulkano_win::CreationError> for Errors {
+    fn from(err: vulkano_win::CreationError) -> Self {
+        match err {
+            vulkano_win::CreationError::SurfaceCreationError(err) => err.into(),
+            vulkano_win::CreationError::WindowCreationError(err) => Errors::WindowCreation(err.to_string()),
+        }
+    }
+}
+
+impl From<SurfaceCreationError> for Errors {
+    fn from(err: SurfaceCreationError) -> Self {
+        match err {
+            SurfaceCreationError::OomError(err) => Errors::OutOfMemory(err),
+            err => Errors::Surface(err.to_string()),
+        }
+    }
+}
+
+impl From<SwapchainCreationError> for Errors {
+    fn from(err: SwapchainCreationError) -> Self {
+        match err {
+            SwapchainCreationError::OomError(err) => Errors::OutOfMemory(err),
+            SwapchainCreationError::DeviceLost => Errors::DeviceLost,
+            SwapchainCreationError::SurfaceLost => Errors::Surface(err.to_string()),
+            err => Errors::Swapchain(err.to_string()),
+        }
+    }
+}
+
+impl From<AcquireError> for Errors {
+    fn from(err: AcquireError) -> Self {
+        match err {
+            AcquireError::OomError(err) => Errors::OutOfMemory(err),
+            AcquireError::DeviceLost => Errors::DeviceLost,
+            AcquireError::SurfaceLost => Errors::Surface(err.to_string()),
+            err => Errors::Swapchain(err.to_string()),
+        }
+    }
+}
+
+impl From<FlushError> for Errors {
+    fn from(err: FlushError) -> Self {
+        match err {
+            FlushError::OomError(err) => Errors::OutOfMemory(err),
+            FlushError::DeviceLost => Errors::DeviceLost,
+            FlushError::SurfaceLost => Errors::Surface(err.to_string()),
+            FlushError::OutOfDate => Errors::Swapchain(err.to_string()),
+            err => Errors::Graphics(err.to_string()),
+        }
+    }
+}
+
+impl From<ShaderCreationError> for Errors {
+    fn from(err: ShaderCreationError) -> Self {
+        match err {
+            ShaderCreationError::OomError(err) => Errors::OutOfMemory(err),
+            err => Errors::ShaderLoad(err.to_string()),
+        }
+    }
+}
+
+impl From<AllocationCreationError> for Errors {
+    fn from(err: AllocationCreationError) -> Self {
+        match err {
+            AllocationCreationError::VulkanError(err) => err.into(),
+            err => Errors::Graphics(err.to_string()),
+        }
+    }
+}
+
+impl From<BufferError> for Errors {
+    fn from(err: BufferError) -> Self {
+        match err {
+            BufferError::VulkanError(err) => err.into(),
+            BufferError::AllocError(err) => err.into(),
+            err => Errors::Graphics(err.to_string()),
+        }
+    }
+}
+
+impl From<ImageError> for Errors {
+    fn from(err: ImageError) -> Self {
+        match err {
+            ImageError::VulkanError(err) => err.into(),
+            ImageError::AllocError(err) => err.into(),
+            err => Errors::Graphics(err.to_string()),
+        }
+    }
+}
+
+impl From<ImmutableImageCreationError> for Errors {
+    fn from(err: ImmutableImageCreationError) -> Self {
+        match err {
+            ImmutableImageCreationError::ImageCreationError(err) => err.into(),
+            ImmutableImageCreationError::AllocError(err) => err.into(),
+            ImmutableImageCreationError::CommandBufferBeginError(err) => err.into(),
+            err => Errors::Graphics(err.to_string()),
+        }
+    }
+}
+
+impl From<image::ImageError> for Errors {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => Errors::Io(err),
+            err => Errors::ImageDecode(err.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Errors {
+    fn from(err: std::io::Error) -> Self {
+        Errors::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for Errors {
+    fn from(err: toml::de::Error) -> Self {
+        Errors::Config(err.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for Errors {
+    fn from(err: toml::ser::Error) -> Self {
+        Errors::Config(err.to_string())
+    }
+}
+
+/// Errors that carry an out-of-memory case and are otherwise only reported.
+macro_rules! oom_or_graphics_error {
+    ($($error:ident),* $(,)?) => {
+        $(
+            impl From<$error> for Errors {
+                fn from(err: $error) -> Self {
+                    match err {
+                        $error::OomError(err) => Errors::OutOfMemory(err),
+                        err => Errors::Graphics(err.to_string()),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+oom_or_graphics_error!(
+    GraphicsPipelineCreationError,
+    ComputePipelineCreationError,
+    RenderPassCreationError,
+    FramebufferCreationError,
+    ImageViewCreationError,
+    CommandBufferBeginError,
+    BuildError,
+    DescriptorSetCreationError,
+    SamplerCreationError,
+);
+
+/// Errors raised while recording or submitting commands.
+macro_rules! graphics_error {
+    ($($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for Errors {
+                fn from(err: $error) -> Self {
+                    Errors::Graphics(err.to_string())
+                }
+            }
+        )*
+    };
+}
+
+graphics_error!(
+    RenderPassError,
+    PipelineExecutionError,
+    CopyError,
+    CommandBufferExecError,
+    DebugUtilsMessengerCreationError,
+);
@@ -0,0 +1,142 @@
+use glam::{Mat4, Quat, Vec3};
+use vulkano::buffer::BufferContents;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// `fov_y` is the vertical field of view in radians.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// `height` is the visible height in world units; the width follows the aspect ratio.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+/// A viewpoint into the scene. Looks down its local -Z axis with +Y up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub projection: Projection,
+}
+
+impl Camera {
+    pub fn perspective(fov_y: f32, near: f32, far: f32) -> Camera {
+        Camera::new(Projection::Perspective { fov_y, near, far })
+    }
+
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Camera {
+        Camera::new(Projection::Orthographic { height, near, far })
+    }
+
+    fn new(projection: Projection) -> Camera {
+        Camera { position: Vec3::ZERO, rotation: Quat::IDENTITY, projection }
+    }
+
+    pub fn with_position(mut self, position: Vec3) -> Camera {
+        self.position = position;
+        self
+    }
+
+    /// Turns the camera towards `target`.
+    pub fn look_at(mut self, target: Vec3, up: Vec3) -> Camera {
+        let view = Mat4::look_at_rh(self.position, target, up);
+        self.rotation = Quat::from_mat4(&view.inverse());
+        self
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.position).inverse()
+    }
+
+    /// Projection into Vulkan clip space: Y points down and depth goes from 0
+    /// at the near plane to 1 at the far plane.
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        let projection = match self.projection {
+            Projection::Perspective { fov_y, near, far } => Mat4::perspective_rh(fov_y, aspect_ratio, near, far),
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, near, far)
+            }
+        };
+
+        Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0)) * projection
+    }
+
+    pub fn view_projection_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        self.projection_matrix(aspect_ratio) * self.view_matrix()
+    }
+}
+
+/// Per-frame camera data, laid out to match the `Camera` uniform block in
+/// the vertex shader.
+#[derive(BufferContents, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct CameraUniform {
+    pub view: [[f32; 4]; 4],
+    pub projection: [[f32; 4]; 4],
+    pub view_projection: [[f32; 4]; 4],
+    /// World-space position; `w` is unused.
+    pub position: [f32; 4],
+}
+
+impl CameraUniform {
+    /// Passes positions through unchanged, for drawing directly in clip space.
+    pub fn identity() -> CameraUniform {
+        CameraUniform {
+            view: Mat4::IDENTITY.to_cols_array_2d(),
+            projection: Mat4::IDENTITY.to_cols_array_2d(),
+            view_projection: Mat4::IDENTITY.to_cols_array_2d(),
+            position: [0.0; 4],
+        }
+    }
+
+    pub fn new(camera: &Camera, aspect_ratio: f32) -> CameraUniform {
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix(aspect_ratio);
+
+        CameraUniform {
+            view: view.to_cols_array_2d(),
+            projection: projection.to_cols_array_2d(),
+            view_projection: (projection * view).to_cols_array_2d(),
+            position: camera.position.extend(1.0).to_array(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(actual.abs_diff_eq(expected, 1e-5), "{actual} != {expected}");
+    }
+
+    #[test]
+    fn look_at_puts_target_in_front() {
+        let camera = Camera::perspective(FRAC_PI_2, 0.1, 100.0)
+            .with_position(Vec3::new(0.0, 0.0, 5.0))
+            .look_at(Vec3::new(5.0, 0.0, 5.0), Vec3::Y);
+
+        assert_close(camera.view_matrix().transform_point3(Vec3::new(5.0, 0.0, 5.0)), Vec3::new(0.0, 0.0, -5.0));
+    }
+
+    #[test]
+    fn perspective_maps_depth_to_vulkan_range() {
+        let camera = Camera::perspective(FRAC_PI_2, 1.0, 10.0);
+        let view_projection = camera.view_projection_matrix(1.0);
+
+        assert_close(view_projection.project_point3(Vec3::new(0.0, 0.0, -1.0)), Vec3::new(0.0, 0.0, 0.0));
+        assert_close(view_projection.project_point3(Vec3::new(0.0, 0.0, -10.0)), Vec3::new(0.0, 0.0, 1.0));
+        // +Y in the world is up on screen, which is -Y in Vulkan clip space.
+        assert_close(view_projection.project_point3(Vec3::new(0.0, 1.0, -1.0)), Vec3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn orthographic_height_follows_aspect_ratio() {
+        let camera = Camera::orthographic(4.0, 0.0, 10.0);
+        let view_projection = camera.view_projection_matrix(2.0);
+
+        assert_close(view_projection.project_point3(Vec3::new(4.0, 2.0, -10.0)), Vec3::new(1.0, -1.0, 1.0));
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use image::RgbaImage;
+use log::{error, info, warn};
+use vulkano::{buffer::Subbuffer, format::Format};
+
+use crate::errors::Errors;
+
+use super::gpu_resources::GpuResources;
+
+/// Whether red and blue must be swapped to get RGBA, or `None` if images of
+/// `format` can't be captured.
+fn swaps_red_blue(format: Format) -> Option<bool> {
+    match format {
+        Format::R8G8B8A8_SRGB | Format::R8G8B8A8_UNORM | Format::A8B8G8R8_SRGB_PACK32 | Format::A8B8G8R8_UNORM_PACK32 => {
+            Some(false)
+        }
+        Format::B8G8R8A8_SRGB | Format::B8G8R8A8_UNORM => Some(true),
+        _ => None,
+    }
+}
+
+/// Whether `to_rgba_image` can convert images of `format`.
+pub fn can_capture(format: Format) -> bool {
+    swaps_red_blue(format).is_some()
+}
+
+/// Converts pixels read back from a color attachment to RGBA8. Handles the
+/// 8-bit RGBA and BGRA formats; their bytes are already sRGB encoded, so only
+/// the channel order changes. Alpha is made opaque, as windows ignore it.
+pub fn to_rgba_image(format: Format, extent: [u32; 2], data: &[u8]) -> Result<RgbaImage, Errors> {
+    let swap_red_blue = swaps_red_blue(format)
+        .ok_or_else(|| Errors::Graphics(format!("capturing {format:?} images is not supported")))?;
+
+    let mut pixels = data.to_vec();
+    for pixel in pixels.chunks_exact_mut(4) {
+        if swap_red_blue {
+            pixel.swap(0, 2);
+        }
+        pixel[3] = u8::MAX;
+    }
+
+    RgbaImage::from_raw(extent[0], extent[1], pixels)
+        .ok_or_else(|| Errors::Graphics("captured image has the wrong size".to_string()))
+}
+
+/// Encodes and writes PNG files on a background thread, so capturing every
+/// frame does not stall rendering on compression.
+struct PngWriter {
+    sender: Option<Sender<(PathBuf, RgbaImage)>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PngWriter {
+    fn new() -> PngWriter {
+        let (sender, receiver) = mpsc::channel::<(PathBuf, RgbaImage)>();
+        let thread = thread::spawn(move || {
+            for (path, image) in receiver {
+                if let Err(err) = Self::write(&path, &image) {
+                    error!("failed to write {}: {err}", path.display());
+                }
+            }
+        });
+
+        PngWriter { sender: Some(sender), thread: Some(thread) }
+    }
+
+    fn write(path: &Path, image: &RgbaImage) -> Result<(), Errors> {
+        if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            fs::create_dir_all(directory)?;
+        }
+
+        Ok(image.save_with_format(path, image::ImageFormat::Png)?)
+    }
+
+    fn send(&self, path: PathBuf, image: RgbaImage) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send((path, image));
+        }
+    }
+
+    /// Waits until the queued images are written and stops the thread.
+    fn finish(&mut self) {
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PngWriter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// A capture asked for through `Context`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CaptureRequest {
+    /// Saves the next frame, to the screenshot directory without a path.
+    Screenshot(Option<PathBuf>),
+    StartSequence(PathBuf),
+    StopSequence,
+}
+
+struct PendingCapture {
+    buffer: Subbuffer<[u8]>,
+    format: Format,
+    extent: [u32; 2],
+    paths: Vec<PathBuf>,
+}
+
+struct ImageSequence {
+    directory: PathBuf,
+    next_index: u64,
+}
+
+/// Saves presented frames as PNG files: single screenshots on request and
+/// numbered sequences of every frame while recording.
+///
+/// Each captured frame copies its image into a host buffer that is read once
+/// the frame finished on the GPU, so capturing does not wait for the GPU.
+/// Every frame in flight keeps its buffer until the extent changes.
+pub struct FrameCapture {
+    gpu_resources: Arc<GpuResources>,
+    screenshot_dir: PathBuf,
+    screenshots: Vec<PathBuf>,
+    sequence: Option<ImageSequence>,
+    /// Copies recorded into each frame in flight.
+    pending: Vec<Option<PendingCapture>>,
+    buffers: Vec<Option<Subbuffer<[u8]>>>,
+    writer: PngWriter,
+}
+
+impl FrameCapture {
+    /// Screenshots without an explicit path are saved to `screenshot_dir`.
+    pub fn new(
+        gpu_resources: Arc<GpuResources>,
+        frames_in_flight: usize,
+        screenshot_dir: impl Into<PathBuf>,
+    ) -> FrameCapture {
+        FrameCapture {
+            gpu_resources,
+            screenshot_dir: screenshot_dir.into(),
+            screenshots: Vec::new(),
+            sequence: None,
+            pending: (0..frames_in_flight).map(|_| None).collect(),
+            buffers: vec![None; frames_in_flight],
+            writer: PngWriter::new(),
+        }
+    }
+
+    pub fn apply(&mut self, request: CaptureRequest) {
+        match request {
+            CaptureRequest::Screenshot(path) => self.request_screenshot(path),
+            CaptureRequest::StartSequence(directory) => self.start_sequence(directory),
+            CaptureRequest::StopSequence => self.stop_sequence(),
+        }
+    }
+
+    /// Saves the next frame to `path`, or to a timestamped file in the
+    /// screenshot directory.
+    pub fn request_screenshot(&mut self, path: Option<PathBuf>) {
+        let path = path.unwrap_or_else(|| {
+            let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+            self.screenshot_dir.join(format!("screenshot-{millis}.png"))
+        });
+        self.screenshots.push(path);
+    }
+
+    /// Saves every following frame as `frame-000000.png`, `frame-000001.png`,
+    /// ... in `directory` until `stop_sequence`.
+    pub fn start_sequence(&mut self, directory: impl Into<PathBuf>) {
+        let directory = directory.into();
+        info!("recording frames to {}", directory.display());
+        self.sequence = Some(ImageSequence { directory, next_index: 0 });
+    }
+
+    pub fn stop_sequence(&mut self) {
+        if let Some(sequence) = self.sequence.take() {
+            info!("recorded {} frames to {}", sequence.next_index, sequence.directory.display());
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.sequence.is_some()
+    }
+
+    /// Returns a buffer the frame at `frame_index` should copy its image into,
+    /// if it is to be captured. `format` is `None` for images that can't be
+    /// copied from.
+    pub fn begin_frame(
+        &mut self,
+        frame_index: usize,
+        format: Option<Format>,
+        extent: [u32; 2],
+    ) -> Result<Option<Subbuffer<[u8]>>, Errors> {
+        let mut paths = std::mem::take(&mut self.screenshots);
+        if let Some(sequence) = &mut self.sequence {
+            paths.push(sequence.directory.join(format!("frame-{:06}.png", sequence.next_index)));
+            sequence.next_index += 1;
+        }
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        let Some(format) = format.filter(|format| can_capture(*format)) else {
+            warn!("can't capture {format:?} images, skipping {} file(s)", paths.len());
+            return Ok(None);
+        };
+
+        let len = extent[0] as u64 * extent[1] as u64 * 4;
+        let buffer = match &self.buffers[frame_index] {
+            Some(buffer) if buffer.len() == len => buffer.clone(),
+            _ => {
+                let buffer = self.gpu_resources.readback_buffer(len, "frame capture buffer")?;
+                self.buffers[frame_index] = Some(buffer.clone());
+                buffer
+            }
+        };
+        self.pending[frame_index] = Some(PendingCapture { buffer: buffer.clone(), format, extent, paths });
+
+        Ok(Some(buffer))
+    }
+
+    /// Hands the copy made by the frame at `frame_index` to the PNG writer.
+    /// Call only once that frame has finished on the GPU.
+    pub fn frame_finished(&mut self, frame_index: usize) -> Result<(), Errors> {
+        let Some(capture) = self.pending[frame_index].take() else {
+            return Ok(());
+        };
+
+        let image = to_rgba_image(capture.format, capture.extent, &capture.buffer.read()?)?;
+        let (last, rest) = capture.paths.split_last().expect("captures have at least one path");
+        for path in rest {
+            self.writer.send(path.clone(), image.clone());
+        }
+        self.writer.send(last.clone(), image);
+
+        Ok(())
+    }
+
+    /// Drops the capture of the frame at `frame_index`. Call when that frame
+    /// was never submitted, so its buffer is not written out.
+    pub fn cancel_frame(&mut self, frame_index: usize) {
+        if let Some(capture) = self.pending[frame_index].take() {
+            warn!("frame {frame_index} was not submitted, skipping {} capture file(s)", capture.paths.len());
+        }
+    }
+
+    /// Writes out every pending capture and waits for the files. Call once
+    /// all frames have finished; later captures are dropped.
+    pub fn finish(&mut self) -> Result<(), Errors> {
+        self.stop_sequence();
+        let result = (0..self.pending.len()).try_for_each(|frame_index| self.frame_finished(frame_index));
+        self.writer.finish();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bgra_is_converted_to_opaque_rgba() {
+        let bgra = [10, 20, 30, 0, 40, 50, 60, 128];
+
+        let image = to_rgba_image(Format::B8G8R8A8_SRGB, [2, 1], &bgra).unwrap();
+        assert_eq!(image.as_raw(), &[30, 20, 10, 255, 60, 50, 40, 255]);
+
+        let image = to_rgba_image(Format::R8G8B8A8_UNORM, [1, 2], &bgra).unwrap();
+        assert_eq!(image.as_raw(), &[10, 20, 30, 255, 40, 50, 60, 255]);
+    }
+
+    #[test]
+    fn unsupported_formats_are_rejected() {
+        assert!(can_capture(Format::B8G8R8A8_UNORM));
+        assert!(!can_capture(Format::R16G16B16A16_SFLOAT));
+        assert!(!can_capture(Format::A2B10G10R10_UNORM_PACK32));
+        assert!(to_rgba_image(Format::R8G8B8A8_SRGB, [2, 2], &[0; 4]).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferContents, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::WriteDescriptorSet,
+    image::ImageViewAbstract,
+    pipeline::PipelineLayout,
+    sampler::Sampler,
+};
+
+use crate::errors::Errors;
+
+use super::{pipeline_cache, shader::Shader, texture::Texture};
+
+/// When a compute pass runs relative to the frame's draw commands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputeStage {
+    /// Before the render pass, e.g. to simulate particles or cull objects
+    /// drawn in the same frame.
+    BeforeGraphics,
+    /// After the render pass, e.g. to process images the frame produced.
+    AfterGraphics,
+    /// On the compute queue where the device has one, submitted before the
+    /// frame, so it can overlap the previous frame's graphics work. The frame
+    /// waits for it before drawing. Resources it writes must not be in use by
+    /// frames still in flight, e.g. keep one copy per frame in flight.
+    Async,
+}
+
+/// Workgroups needed to cover `threads` invocations with workgroups of
+/// `local_size`, the shader's `local_size_x/y/z`.
+pub fn workgroup_count(threads: [u32; 3], local_size: [u32; 3]) -> [u32; 3] {
+    [0, 1, 2].map(|axis| threads[axis].div_ceil(local_size[axis].max(1)))
+}
+
+#[derive(Clone)]
+enum ComputeBinding {
+    /// A uniform or storage buffer.
+    Buffer(Subbuffer<[u8]>),
+    /// A storage or sampled image.
+    Image(Arc<dyn ImageViewAbstract>),
+    ImageSampler(Arc<dyn ImageViewAbstract>, Arc<Sampler>),
+}
+
+/// Records the pass's push constants for the pipeline layout.
+type RecordPushConstants =
+    Arc<dyn Fn(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, Arc<PipelineLayout>) + Send + Sync>;
+
+#[derive(Clone)]
+struct PushConstants {
+    size: u32,
+    record: RecordPushConstants,
+}
+
+/// A compute shader dispatch with the resources it reads and writes.
+///
+/// Resources are bound to descriptor set 0 at the given bindings. Passes are
+/// added to a frame with `DrawList::dispatch`; the command buffer builder
+/// inserts the barriers between them and the draws using the same resources.
+#[derive(Clone)]
+pub struct ComputePass {
+    shader: Arc<Shader>,
+    stage: ComputeStage,
+    workgroups: [u32; 3],
+    bindings: Vec<(u32, ComputeBinding)>,
+    push_constants: Option<PushConstants>,
+}
+
+impl ComputePass {
+    /// A single workgroup of `shader` dispatched before graphics.
+    pub fn new(shader: &Arc<Shader>) -> ComputePass {
+        ComputePass {
+            shader: shader.clone(),
+            stage: ComputeStage::BeforeGraphics,
+            workgroups: [1, 1, 1],
+            bindings: Vec::new(),
+            push_constants: None,
+        }
+    }
+
+    pub fn with_stage(mut self, stage: ComputeStage) -> ComputePass {
+        self.stage = stage;
+        self
+    }
+
+    pub fn with_workgroups(mut self, workgroups: [u32; 3]) -> ComputePass {
+        self.workgroups = workgroups;
+        self
+    }
+
+    /// Dispatches enough workgroups of `local_size` to run at least `threads`
+    /// invocations; the shader has to skip the ones past the end.
+    pub fn with_threads(self, threads: [u32; 3], local_size: [u32; 3]) -> ComputePass {
+        self.with_workgroups(workgroup_count(threads, local_size))
+    }
+
+    /// Binds a uniform or storage buffer.
+    pub fn with_buffer<T: ?Sized>(mut self, binding: u32, buffer: Subbuffer<T>) -> ComputePass {
+        self.bindings.push((binding, ComputeBinding::Buffer(buffer.into_bytes())));
+        self
+    }
+
+    /// Binds a storage image, or a sampled image for a separate sampler.
+    pub fn with_image(mut self, binding: u32, image_view: Arc<dyn ImageViewAbstract>) -> ComputePass {
+        self.bindings.push((binding, ComputeBinding::Image(image_view)));
+        self
+    }
+
+    /// Binds `texture` as a combined image sampler.
+    pub fn with_texture(mut self, binding: u32, texture: &Arc<Texture>) -> ComputePass {
+        self.bindings
+            .push((binding, ComputeBinding::ImageSampler(texture.view.clone(), texture.sampler.clone())));
+        self
+    }
+
+    /// Sets the push constant block, laid out to match the shader's.
+    pub fn with_push_constants<T: BufferContents + Copy + Send + Sync>(mut self, push_constants: T) -> ComputePass {
+        self.push_constants = Some(PushConstants {
+            size: size_of::<T>() as u32,
+            record: Arc::new(move |builder, layout| {
+                builder.push_constants(layout, 0, push_constants);
+            }),
+        });
+        self
+    }
+
+    pub fn shader(&self) -> &Arc<Shader> {
+        &self.shader
+    }
+
+    pub fn stage(&self) -> ComputeStage {
+        self.stage
+    }
+
+    pub fn workgroups(&self) -> [u32; 3] {
+        self.workgroups
+    }
+
+    pub(crate) fn descriptor_writes(&self) -> Vec<WriteDescriptorSet> {
+        self.bindings
+            .iter()
+            .map(|(binding, resource)| match resource {
+                ComputeBinding::Buffer(buffer) => WriteDescriptorSet::buffer(*binding, buffer.clone()),
+                ComputeBinding::Image(image_view) => WriteDescriptorSet::image_view(*binding, image_view.clone()),
+                ComputeBinding::ImageSampler(image_view, sampler) => {
+                    WriteDescriptorSet::image_view_sampler(*binding, image_view.clone(), sampler.clone())
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn has_bindings(&self) -> bool {
+        !self.bindings.is_empty()
+    }
+
+    /// Fails if the shader's push constant block doesn't match the one set.
+    pub(crate) fn record_push_constants(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        layout: Arc<PipelineLayout>,
+    ) -> Result<(), Errors> {
+        let Some(push_constants) = &self.push_constants else {
+            return Ok(());
+        };
+        if !push_constants.size.is_multiple_of(4) || !pipeline_cache::covers_push_constants(&layout, push_constants.size) {
+            return Err(Errors::ShaderLoad(format!(
+                "compute shader push constants don't match the {} bytes set on the pass",
+                push_constants.size
+            )));
+        }
+        (push_constants.record)(builder, layout);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workgroups_cover_every_thread() {
+        assert_eq!(workgroup_count([1024, 1, 1], [64, 1, 1]), [16, 1, 1]);
+        assert_eq!(workgroup_count([1000, 720, 1], [16, 16, 1]), [63, 45, 1]);
+        assert_eq!(workgroup_count([0, 5, 5], [8, 0, 1]), [0, 5, 5]);
+    }
+}
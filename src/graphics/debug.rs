@@ -0,0 +1,122 @@
+use std::{env, sync::Arc};
+
+use log::{debug, error, info, trace, warn, Level};
+use vulkano::{
+    device::DeviceOwned,
+    instance::{
+        debug::{
+            DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessenger, DebugUtilsMessengerCreateInfo,
+            Message,
+        },
+        Instance, InstanceExtensions,
+    },
+    VulkanLibrary, VulkanObject,
+};
+
+use crate::errors::Errors;
+
+/// Set to `1` or `true` to enable Vulkan debugging without changing code.
+pub const DEBUG_ENV: &str = "LUMR_VULKAN_DEBUG";
+
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Whether `LUMR_VULKAN_DEBUG` asks for Vulkan debugging.
+pub fn debug_requested_by_env() -> bool {
+    env::var(DEBUG_ENV).is_ok_and(|value| matches!(value.trim(), "1" | "true" | "on" | "yes"))
+}
+
+/// Layers and extensions to enable for debugging, limited to what the
+/// installed Vulkan library offers.
+pub struct DebugSupport {
+    pub layers: Vec<String>,
+    pub extensions: InstanceExtensions,
+}
+
+impl DebugSupport {
+    pub fn query(library: &VulkanLibrary) -> Result<DebugSupport, Errors> {
+        let has_validation_layer = library.layer_properties()?.any(|layer| layer.name() == VALIDATION_LAYER);
+        let layers = if has_validation_layer {
+            vec![VALIDATION_LAYER.to_string()]
+        } else {
+            warn!("Vulkan debugging requested, but {VALIDATION_LAYER} is not installed");
+            Vec::new()
+        };
+
+        // The validation layer brings its own copy of the extension.
+        let supported_extensions = library.supported_extensions_with_layers(layers.iter().map(String::as_str))?;
+        let extensions = InstanceExtensions {
+            ext_debug_utils: supported_extensions.ext_debug_utils,
+            ..InstanceExtensions::empty()
+        };
+
+        Ok(DebugSupport { layers, extensions })
+    }
+}
+
+/// The log level for messages of `severity`.
+pub fn log_level(severity: DebugUtilsMessageSeverity) -> Level {
+    if severity.intersects(DebugUtilsMessageSeverity::ERROR) {
+        Level::Error
+    } else if severity.intersects(DebugUtilsMessageSeverity::WARNING) {
+        Level::Warn
+    } else if severity.intersects(DebugUtilsMessageSeverity::INFO) {
+        Level::Info
+    } else {
+        Level::Trace
+    }
+}
+
+fn log_message(message: &Message<'_>) {
+    let layer = message.layer_prefix.unwrap_or("vulkan");
+    match log_level(message.severity) {
+        Level::Error => error!(target: "vulkan", "[{layer}] {}", message.description),
+        Level::Warn => warn!(target: "vulkan", "[{layer}] {}", message.description),
+        Level::Info => info!(target: "vulkan", "[{layer}] {}", message.description),
+        Level::Debug => debug!(target: "vulkan", "[{layer}] {}", message.description),
+        Level::Trace => trace!(target: "vulkan", "[{layer}] {}", message.description),
+    }
+}
+
+/// Forwards validation and driver messages to `log` under the `vulkan` target.
+/// Messages stop when the returned messenger is dropped.
+pub fn create_debug_messenger(instance: &Arc<Instance>) -> Result<DebugUtilsMessenger, Errors> {
+    let create_info = DebugUtilsMessengerCreateInfo {
+        message_severity: DebugUtilsMessageSeverity::ERROR
+            | DebugUtilsMessageSeverity::WARNING
+            | DebugUtilsMessageSeverity::INFO
+            | DebugUtilsMessageSeverity::VERBOSE,
+        message_type: DebugUtilsMessageType::GENERAL
+            | DebugUtilsMessageType::VALIDATION
+            | DebugUtilsMessageType::PERFORMANCE,
+        ..DebugUtilsMessengerCreateInfo::user_callback(Arc::new(log_message))
+    };
+
+    // The callback only formats and logs, it never calls into Vulkan.
+    Ok(unsafe { DebugUtilsMessenger::new(instance.clone(), create_info)? })
+}
+
+/// Names `object` in validation messages and graphics debuggers. Does nothing
+/// unless the instance was created with debugging enabled.
+pub fn set_object_name<T: VulkanObject + DeviceOwned>(object: &T, name: &str) {
+    let device = object.device();
+    if !device.instance().enabled_extensions().ext_debug_utils {
+        return;
+    }
+
+    if let Err(err) = device.set_debug_utils_object_name(object, Some(name)) {
+        warn!("failed to name Vulkan object {name:?}: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severities_map_to_log_levels() {
+        assert_eq!(log_level(DebugUtilsMessageSeverity::ERROR), Level::Error);
+        assert_eq!(log_level(DebugUtilsMessageSeverity::WARNING), Level::Warn);
+        assert_eq!(log_level(DebugUtilsMessageSeverity::INFO), Level::Info);
+        assert_eq!(log_level(DebugUtilsMessageSeverity::VERBOSE), Level::Trace);
+    }
+}
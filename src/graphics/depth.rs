@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use vulkano::{
+    device::physical::PhysicalDevice,
+    format::{ClearValue, Format, FormatFeatures},
+    image::{view::ImageView, AttachmentImage, ImageAccess, ImageAspects, SampleCount},
+    pipeline::{graphics::depth_stencil::{CompareOp, DepthState, DepthStencilState}, StateMode},
+};
+
+use crate::errors::Errors;
+
+use super::{debug, gpu_resources::GpuResources};
+
+/// Depth formats in order of preference.
+const DEPTH_FORMATS: [Format; 4] = [
+    Format::D32_SFLOAT,
+    Format::D32_SFLOAT_S8_UINT,
+    Format::D24_UNORM_S8_UINT,
+    Format::D16_UNORM,
+];
+
+/// How a pipeline uses the depth buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DepthConfig {
+    /// Discard fragments that fail `compare_op` against the stored depth.
+    pub test: bool,
+    /// Store the depth of fragments that pass.
+    pub write: bool,
+    pub compare_op: CompareOp,
+}
+
+impl Default for DepthConfig {
+    /// Tests and writes depth. Uses `LessOrEqual` so meshes at the same depth
+    /// still draw over each other in submission order.
+    fn default() -> Self {
+        DepthConfig { test: true, write: true, compare_op: CompareOp::LessOrEqual }
+    }
+}
+
+impl DepthConfig {
+    /// Draws everything in submission order, ignoring the depth buffer.
+    pub fn disabled() -> DepthConfig {
+        DepthConfig { test: false, write: false, compare_op: CompareOp::Always }
+    }
+
+    pub fn depth_stencil_state(&self) -> DepthStencilState {
+        if !self.test && !self.write {
+            return DepthStencilState::disabled();
+        }
+
+        DepthStencilState {
+            depth: Some(DepthState {
+                enable_dynamic: false,
+                write_enable: StateMode::Fixed(self.write),
+                compare_op: StateMode::Fixed(if self.test { self.compare_op } else { CompareOp::Always }),
+            }),
+            ..DepthStencilState::disabled()
+        }
+    }
+}
+
+/// Picks the first depth format the device can use as an optimally tiled
+/// depth attachment.
+pub fn find_depth_format(physical_device: &PhysicalDevice) -> Result<Format, Errors> {
+    for format in DEPTH_FORMATS {
+        let properties = physical_device.format_properties(format)?;
+        if properties.optimal_tiling_features.intersects(FormatFeatures::DEPTH_STENCIL_ATTACHMENT) {
+            return Ok(format);
+        }
+    }
+
+    Err(Errors::Graphics("no supported depth attachment format".to_string()))
+}
+
+/// Value that resets a depth attachment of `format` to the far plane.
+pub fn depth_clear_value(format: Format) -> ClearValue {
+    if format.aspects().intersects(ImageAspects::STENCIL) {
+        ClearValue::DepthStencil((1.0, 0))
+    } else {
+        ClearValue::Depth(1.0)
+    }
+}
+
+pub fn create_depth_image(
+    gpu_resources: &GpuResources,
+    format: Format,
+    dimensions: [u32; 2],
+    samples: SampleCount,
+) -> Result<Arc<ImageView<AttachmentImage>>, Errors> {
+    let image =
+        AttachmentImage::transient_multisampled(gpu_resources.memory_allocator().as_ref(), dimensions, samples, format)?;
+    debug::set_object_name(image.inner().image.as_ref(), "depth attachment");
+    gpu_resources.track_image(image.inner().image);
+
+    Ok(ImageView::new_default(image)?)
+}
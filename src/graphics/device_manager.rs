@@ -1,8 +1,9 @@
 use std::sync::Arc;
 
-use vulkano::device::physical::{PhysicalDevice, PhysicalDeviceType};
+use log::info;
+use vulkano::device::physical::PhysicalDevice;
 use vulkano::device::{
-    Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo, QueueFlags,
+    Device, DeviceCreateInfo, DeviceExtensions, Features, Queue, QueueCreateInfo, QueueFlags,
 };
 
 use vulkano::{
@@ -10,66 +11,165 @@ use vulkano::{
     swapchain::Surface,
 };
 
+use crate::errors::Errors;
+
+use super::device_selection::{DeviceInfo, DeviceSelection};
+
 pub struct DeviceManager {
     pub physical_device: Arc<PhysicalDevice>,
     pub device: Arc<Device>,
+    /// Graphics queue, also used for presenting.
     pub queue: Arc<Queue>,
+    /// Queue of a transfer-only family, if the device has one.
+    pub transfer_queue: Option<Arc<Queue>>,
+    /// Queue of a compute family without graphics, if the device has one.
+    pub compute_queue: Option<Arc<Queue>>,
 }
 
 impl DeviceManager {
-    pub fn new(instance: &Arc<Instance>, surface: &Arc<Surface>) -> Arc<DeviceManager> {
+    pub fn new(
+        instance: &Arc<Instance>,
+        surface: &Arc<Surface>,
+        selection: &DeviceSelection,
+    ) -> Result<Arc<DeviceManager>, Errors> {
         let device_extensions = DeviceExtensions {
             khr_swapchain: true,
             ..DeviceExtensions::empty()
         };
-    
+
+        Self::create(instance, Some(surface), device_extensions, selection)
+    }
+
+    /// Creates a device for offscreen rendering, without requiring presentation support.
+    pub fn new_headless(instance: &Arc<Instance>, selection: &DeviceSelection) -> Result<Arc<DeviceManager>, Errors> {
+        Self::create(instance, None, DeviceExtensions::empty(), selection)
+    }
+
+    fn create(
+        instance: &Arc<Instance>,
+        surface: Option<&Arc<Surface>>,
+        device_extensions: DeviceExtensions,
+        selection: &DeviceSelection,
+    ) -> Result<Arc<DeviceManager>, Errors> {
+        let selection = selection.clone().with_env_override()?;
         let (physical_device, queue_family_index) =
-            Self::select_physical_device(&instance, &surface, &device_extensions);
+            Self::select_physical_device(instance, surface, &device_extensions, &selection)?;
+
+        // On top of the required ones, optional features are enabled whenever
+        // the device has them.
+        let enabled_features = Features {
+            sampler_anisotropy: physical_device.supported_features().sampler_anisotropy,
+            ..selection.required_features
+        };
     
+        let queue_flags: Vec<_> = physical_device
+            .queue_family_properties()
+            .iter()
+            .map(|queue_family| queue_family.queue_flags)
+            .collect();
+        let (transfer_family, compute_family) = dedicated_queue_families(&queue_flags, queue_family_index);
+        let queue_families: Vec<_> = [Some(queue_family_index), transfer_family, compute_family]
+            .into_iter()
+            .flatten()
+            .collect();
+
         let (device, mut queues) = Device::new(
             physical_device.clone(),
             DeviceCreateInfo {
-                queue_create_infos: vec![QueueCreateInfo {
-                    queue_family_index,
-                    ..Default::default()
-                }],
+                queue_create_infos: queue_families
+                    .iter()
+                    .map(|&queue_family_index| QueueCreateInfo {
+                        queue_family_index,
+                        ..Default::default()
+                    })
+                    .collect(),
                 enabled_extensions: device_extensions, // new
+                enabled_features,
                 ..Default::default()
             },
-        )
-        .expect("failed to create device");
-    
+        )?;
+
+        // Queues come back in the order of their create infos.
         let queue = queues.next().unwrap();
+        let transfer_queue = transfer_family.and_then(|_| queues.next());
+        let compute_queue = compute_family.and_then(|_| queues.next());
+        info!(
+            "using queue families {queue_family_index} for graphics, {} for transfers and {} for compute",
+            transfer_queue.as_ref().unwrap_or(&queue).queue_family_index(),
+            compute_queue.as_ref().unwrap_or(&queue).queue_family_index(),
+        );
 
-        Arc::new(DeviceManager { physical_device, device, queue })
+        Ok(Arc::new(DeviceManager { physical_device, device, queue, transfer_queue, compute_queue }))
+    }
+
+    /// The queue uploads are copied on: the transfer queue, or the graphics
+    /// queue without one.
+    pub fn upload_queue(&self) -> &Arc<Queue> {
+        self.transfer_queue.as_ref().unwrap_or(&self.queue)
+    }
+
+    /// The queue compute work can run on asynchronously to graphics: the
+    /// compute queue, or the graphics queue without one.
+    pub fn async_compute_queue(&self) -> &Arc<Queue> {
+        self.compute_queue.as_ref().unwrap_or(&self.queue)
     }
 
     fn select_physical_device(
         instance: &Arc<Instance>,
-        surface: &Arc<Surface>,
+        surface: Option<&Arc<Surface>>,
         device_extensions: &DeviceExtensions,
-    ) -> (Arc<PhysicalDevice>, u32) {
-        instance
-            .enumerate_physical_devices()
-            .expect("failed to enumerate physical devices")
-            .filter(|p| p.supported_extensions().contains(device_extensions))
-            .filter_map(|p| {
-                p.queue_family_properties()
-                    .iter()
-                    .enumerate()
-                    .position(|(i, q)| {
-                        q.queue_flags.contains(QueueFlags::GRAPHICS)
-                            && p.surface_support(i as u32, surface).unwrap_or(false)
-                    })
-                    .map(|q| (p, q as u32))
-            })
-            .min_by_key(|(p, _)| match p.properties().device_type {
-                PhysicalDeviceType::DiscreteGpu => 0,
-                PhysicalDeviceType::IntegratedGpu => 1,
-                PhysicalDeviceType::VirtualGpu => 2,
-                PhysicalDeviceType::Cpu => 3,
-                _ => 4,
-            })
-            .expect("no device available")
+        selection: &DeviceSelection,
+    ) -> Result<(Arc<PhysicalDevice>, u32), Errors> {
+        let physical_devices: Vec<_> = instance.enumerate_physical_devices()?.collect();
+        let candidates: Vec<_> = physical_devices
+            .iter()
+            .enumerate()
+            .map(|(index, physical_device)| DeviceInfo::query(index, physical_device, surface.map(Arc::as_ref)))
+            .collect();
+        for candidate in &candidates {
+            info!("found Vulkan device {candidate}");
+        }
+
+        let chosen = selection.choose(&candidates, device_extensions)?;
+        info!("using Vulkan device {chosen}");
+        let queue_family_index = chosen
+            .queue_family_index
+            .ok_or_else(|| Errors::NoSuitableDevice(format!("{chosen} has no graphics queue")))?;
+
+        Ok((physical_devices[chosen.index].clone(), queue_family_index))
+    }
+}
+
+/// Picks the families for a dedicated transfer queue, one that supports
+/// neither graphics nor compute, and a dedicated compute queue, one without
+/// graphics, besides `graphics_family`. Returns `(transfer, compute)`.
+fn dedicated_queue_families(queue_flags: &[QueueFlags], graphics_family: u32) -> (Option<u32>, Option<u32>) {
+    let find = |wanted: QueueFlags, unwanted: QueueFlags| {
+        queue_flags
+            .iter()
+            .zip(0..)
+            .find(|&(flags, index)| index != graphics_family && flags.contains(wanted) && !flags.intersects(unwanted))
+            .map(|(_, index)| index)
+    };
+
+    (
+        find(QueueFlags::TRANSFER, QueueFlags::GRAPHICS | QueueFlags::COMPUTE),
+        find(QueueFlags::COMPUTE, QueueFlags::GRAPHICS),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedicated_queue_families_skip_graphics() {
+        let all = QueueFlags::GRAPHICS | QueueFlags::COMPUTE | QueueFlags::TRANSFER;
+        let families = [all, QueueFlags::TRANSFER, QueueFlags::COMPUTE | QueueFlags::TRANSFER];
+        assert_eq!(dedicated_queue_families(&families, 0), (Some(1), Some(2)));
+
+        assert_eq!(dedicated_queue_families(&[all], 0), (None, None));
+        assert_eq!(dedicated_queue_families(&[all, QueueFlags::COMPUTE | QueueFlags::TRANSFER], 0), (None, Some(1)));
+        assert_eq!(dedicated_queue_families(&[QueueFlags::TRANSFER, all], 1), (Some(0), None));
     }
 }
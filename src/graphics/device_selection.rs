@@ -0,0 +1,381 @@
+use std::{env, fmt, str::FromStr};
+
+use log::info;
+use vulkano::{
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType},
+        DeviceExtensions, Features, QueueFlags,
+    },
+    memory::MemoryHeapFlags,
+    swapchain::Surface,
+    Version,
+};
+
+use crate::errors::Errors;
+
+/// Set to a device index, name or UUID to force that device, e.g.
+/// `LUMR_DEVICE=1`, `LUMR_DEVICE=name:intel` or `LUMR_DEVICE=uuid:<hex>`.
+pub const DEVICE_ENV: &str = "LUMR_DEVICE";
+
+/// A specific device to use instead of the best scoring one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceOverride {
+    /// Position in the instance's device list, as shown in the log.
+    Index(usize),
+    /// Case-insensitive part of the device name.
+    Name(String),
+    Uuid([u8; 16]),
+}
+
+impl DeviceOverride {
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        match self {
+            DeviceOverride::Index(index) => info.index == *index,
+            DeviceOverride::Name(name) => info.name.to_lowercase().contains(&name.to_lowercase()),
+            DeviceOverride::Uuid(uuid) => info.uuid == Some(*uuid),
+        }
+    }
+}
+
+impl FromStr for DeviceOverride {
+    type Err = Errors;
+
+    /// Accepts `index:`, `name:` and `uuid:` prefixes. Without one, digits
+    /// are an index, 32 hex digits a UUID and anything else a name.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Some(index) = value.strip_prefix("index:") {
+            return index
+                .trim()
+                .parse()
+                .map(DeviceOverride::Index)
+                .map_err(|_| Errors::Config(format!("invalid device index {index:?}")));
+        }
+        if let Some(name) = value.strip_prefix("name:") {
+            return Ok(DeviceOverride::Name(name.trim().to_string()));
+        }
+        if let Some(uuid) = value.strip_prefix("uuid:") {
+            return parse_uuid(uuid)
+                .map(DeviceOverride::Uuid)
+                .ok_or_else(|| Errors::Config(format!("invalid device UUID {uuid:?}")));
+        }
+
+        if value.is_empty() {
+            Err(Errors::Config("empty device name".to_string()))
+        } else if let Ok(index) = value.parse() {
+            Ok(DeviceOverride::Index(index))
+        } else if let Some(uuid) = parse_uuid(value) {
+            Ok(DeviceOverride::Uuid(uuid))
+        } else {
+            Ok(DeviceOverride::Name(value.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for DeviceOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceOverride::Index(index) => write!(f, "index {index}"),
+            DeviceOverride::Name(name) => write!(f, "name {name:?}"),
+            DeviceOverride::Uuid(uuid) => write!(f, "UUID {}", format_uuid(uuid)),
+        }
+    }
+}
+
+/// Parses 32 hex digits, ignoring dashes.
+fn parse_uuid(value: &str) -> Option<[u8; 16]> {
+    let digits: Vec<u8> = value.trim().bytes().filter(|byte| *byte != b'-').collect();
+    if digits.len() != 32 {
+        return None;
+    }
+
+    let mut uuid = [0; 16];
+    for (byte, pair) in uuid.iter_mut().zip(digits.chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+
+    Some(uuid)
+}
+
+fn format_uuid(uuid: &[u8; 16]) -> String {
+    uuid.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// What a physical device must offer to be picked.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceSelection {
+    /// Forces one device. `LUMR_DEVICE` takes precedence over it.
+    pub device: Option<DeviceOverride>,
+    /// Features enabled on the device; devices lacking any are rejected.
+    pub required_features: Features,
+    /// Smallest acceptable device local memory, in bytes.
+    pub min_device_memory: u64,
+    /// Smallest acceptable `max_image_dimension2_d` limit.
+    pub min_image_dimension_2d: u32,
+}
+
+impl DeviceSelection {
+    pub fn with_device(mut self, device: DeviceOverride) -> DeviceSelection {
+        self.device = Some(device);
+        self
+    }
+
+    pub fn with_required_features(mut self, required_features: Features) -> DeviceSelection {
+        self.required_features = required_features;
+        self
+    }
+
+    pub fn with_min_device_memory(mut self, min_device_memory: u64) -> DeviceSelection {
+        self.min_device_memory = min_device_memory;
+        self
+    }
+
+    pub fn with_min_image_dimension_2d(mut self, min_image_dimension_2d: u32) -> DeviceSelection {
+        self.min_image_dimension_2d = min_image_dimension_2d;
+        self
+    }
+
+    /// Replaces `device` with the one named by `LUMR_DEVICE`, if it is set.
+    pub fn with_env_override(self) -> Result<DeviceSelection, Errors> {
+        match env::var(DEVICE_ENV) {
+            Ok(value) if !value.trim().is_empty() => Ok(self.with_device(value.parse()?)),
+            _ => Ok(self),
+        }
+    }
+
+    /// Why `info` can't be used; empty if it can.
+    pub fn rejection_reasons(&self, info: &DeviceInfo, required_extensions: &DeviceExtensions) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if let Some(device) = self.device.as_ref().filter(|device| !device.matches(info)) {
+            reasons.push(format!("not the requested device ({device})"));
+        }
+
+        let missing_extensions = required_extensions.difference(&info.supported_extensions);
+        if missing_extensions != DeviceExtensions::empty() {
+            reasons.push(format!("missing extensions {missing_extensions:?}"));
+        }
+        let missing_features = self.required_features.difference(&info.supported_features);
+        if missing_features != Features::empty() {
+            reasons.push(format!("missing features {missing_features:?}"));
+        }
+        if info.queue_family_index.is_none() {
+            reasons.push("no queue family that supports graphics and the surface".to_string());
+        }
+        if info.device_local_memory < self.min_device_memory {
+            reasons.push(format!(
+                "{} MiB of device memory, {} MiB required",
+                info.device_local_memory >> 20,
+                self.min_device_memory >> 20,
+            ));
+        }
+        if info.max_image_dimension_2d < self.min_image_dimension_2d {
+            reasons.push(format!(
+                "2D images limited to {} pixels, {} required",
+                info.max_image_dimension_2d, self.min_image_dimension_2d,
+            ));
+        }
+
+        reasons
+    }
+
+    /// Picks the best scoring device that meets every requirement, or fails
+    /// with the reasons each device was rejected for.
+    pub fn choose<'a>(
+        &self,
+        candidates: &'a [DeviceInfo],
+        required_extensions: &DeviceExtensions,
+    ) -> Result<&'a DeviceInfo, Errors> {
+        let mut best: Option<&DeviceInfo> = None;
+        let mut rejections = Vec::new();
+        for info in candidates {
+            let reasons = self.rejection_reasons(info, required_extensions);
+            if !reasons.is_empty() {
+                info!("rejected Vulkan device #{} {}: {}", info.index, info.name, reasons.join("; "));
+                rejections.push(format!("#{} {}: {}", info.index, info.name, reasons.join("; ")));
+            } else if best.is_none_or(|best| info.score() > best.score()) {
+                best = Some(info);
+            }
+        }
+
+        best.ok_or_else(|| {
+            if rejections.is_empty() {
+                Errors::NoSuitableDevice("no Vulkan devices found".to_string())
+            } else {
+                Errors::NoSuitableDevice(rejections.join("\n"))
+            }
+        })
+    }
+}
+
+/// The properties of a physical device that selection looks at.
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub index: usize,
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    pub uuid: Option<[u8; 16]>,
+    pub api_version: Version,
+    pub driver_version: u32,
+    /// Total size of the device local memory heaps, in bytes.
+    pub device_local_memory: u64,
+    pub max_image_dimension_2d: u32,
+    pub supported_extensions: DeviceExtensions,
+    pub supported_features: Features,
+    /// First queue family with graphics support that can also present to the
+    /// surface, if there is one.
+    pub queue_family_index: Option<u32>,
+}
+
+impl DeviceInfo {
+    pub fn query(index: usize, physical_device: &PhysicalDevice, surface: Option<&Surface>) -> DeviceInfo {
+        let properties = physical_device.properties();
+        let device_local_memory = physical_device
+            .memory_properties()
+            .memory_heaps
+            .iter()
+            .filter(|heap| heap.flags.intersects(MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .sum();
+        let queue_family_index = physical_device
+            .queue_family_properties()
+            .iter()
+            .enumerate()
+            .position(|(i, queue_family)| {
+                queue_family.queue_flags.contains(QueueFlags::GRAPHICS)
+                    && surface.is_none_or(|surface| physical_device.surface_support(i as u32, surface).unwrap_or(false))
+            })
+            .map(|i| i as u32);
+
+        DeviceInfo {
+            index,
+            name: properties.device_name.clone(),
+            device_type: properties.device_type,
+            uuid: properties.device_uuid,
+            api_version: physical_device.api_version(),
+            driver_version: properties.driver_version,
+            device_local_memory,
+            max_image_dimension_2d: properties.max_image_dimension2_d,
+            supported_extensions: *physical_device.supported_extensions(),
+            supported_features: *physical_device.supported_features(),
+            queue_family_index,
+        }
+    }
+
+    /// Higher is better: discrete GPUs first, then the most device memory.
+    pub fn score(&self) -> (u32, u64) {
+        let type_score = match self.device_type {
+            PhysicalDeviceType::DiscreteGpu => 4,
+            PhysicalDeviceType::IntegratedGpu => 3,
+            PhysicalDeviceType::VirtualGpu => 2,
+            PhysicalDeviceType::Cpu => 1,
+            _ => 0,
+        };
+
+        (type_score, self.device_local_memory)
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} {} ({:?}, {} MiB device memory, Vulkan {}, driver {:#x}",
+            self.index,
+            self.name,
+            self.device_type,
+            self.device_local_memory >> 20,
+            self.api_version,
+            self.driver_version,
+        )?;
+        if let Some(uuid) = &self.uuid {
+            write!(f, ", UUID {}", format_uuid(uuid))?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(index: usize, name: &str, device_type: PhysicalDeviceType, memory_mib: u64) -> DeviceInfo {
+        DeviceInfo {
+            index,
+            name: name.to_string(),
+            device_type,
+            uuid: Some([index as u8; 16]),
+            api_version: Version::V1_2,
+            driver_version: 1,
+            device_local_memory: memory_mib << 20,
+            max_image_dimension_2d: 8192,
+            supported_extensions: DeviceExtensions { khr_swapchain: true, ..DeviceExtensions::empty() },
+            supported_features: Features { sampler_anisotropy: true, ..Features::empty() },
+            queue_family_index: Some(0),
+        }
+    }
+
+    fn candidates() -> Vec<DeviceInfo> {
+        vec![
+            device(0, "llvmpipe", PhysicalDeviceType::Cpu, 0),
+            device(1, "Intel UHD Graphics", PhysicalDeviceType::IntegratedGpu, 1024),
+            device(2, "GeForce GTX 1060", PhysicalDeviceType::DiscreteGpu, 3072),
+            device(3, "GeForce RTX 3080", PhysicalDeviceType::DiscreteGpu, 10240),
+        ]
+    }
+
+    #[test]
+    fn overrides_are_parsed() {
+        assert_eq!("2".parse::<DeviceOverride>().unwrap(), DeviceOverride::Index(2));
+        assert_eq!("index: 3".parse::<DeviceOverride>().unwrap(), DeviceOverride::Index(3));
+        assert_eq!("name:1060".parse::<DeviceOverride>().unwrap(), DeviceOverride::Name("1060".to_string()));
+        assert_eq!("GeForce".parse::<DeviceOverride>().unwrap(), DeviceOverride::Name("GeForce".to_string()));
+
+        let uuid = [0xab; 16];
+        let hex = format_uuid(&uuid);
+        assert_eq!(hex.parse::<DeviceOverride>().unwrap(), DeviceOverride::Uuid(uuid));
+        let dashed = format!("uuid:{}-{}", &hex[..8], &hex[8..]);
+        assert_eq!(dashed.parse::<DeviceOverride>().unwrap(), DeviceOverride::Uuid(uuid));
+
+        assert!("index:gpu".parse::<DeviceOverride>().is_err());
+        assert!("uuid:1234".parse::<DeviceOverride>().is_err());
+        assert!(" ".parse::<DeviceOverride>().is_err());
+    }
+
+    #[test]
+    fn best_scoring_device_is_chosen() {
+        let candidates = candidates();
+        let extensions = DeviceExtensions { khr_swapchain: true, ..DeviceExtensions::empty() };
+
+        let chosen = DeviceSelection::default().choose(&candidates, &extensions).unwrap();
+        assert_eq!(chosen.index, 3);
+
+        let selection = DeviceSelection::default().with_device(DeviceOverride::Name("intel".to_string()));
+        assert_eq!(selection.choose(&candidates, &extensions).unwrap().index, 1);
+
+        let selection = DeviceSelection::default().with_min_device_memory(16 << 30);
+        assert!(selection.choose(&candidates, &extensions).is_err());
+    }
+
+    #[test]
+    fn rejections_list_every_reason() {
+        let mut candidates = candidates();
+        candidates[3].queue_family_index = None;
+        let extensions = DeviceExtensions { khr_swapchain: true, ext_robustness2: true, ..DeviceExtensions::empty() };
+        let selection = DeviceSelection::default()
+            .with_device(DeviceOverride::Index(3))
+            .with_required_features(Features { geometry_shader: true, ..Features::empty() })
+            .with_min_image_dimension_2d(16384);
+
+        let reasons = selection.rejection_reasons(&candidates[3], &extensions);
+        assert_eq!(reasons.len(), 4, "{reasons:?}");
+        assert!(reasons[0].contains("robustness2"), "{reasons:?}");
+        assert!(reasons[1].contains("geometryShader"), "{reasons:?}");
+        assert_eq!(selection.rejection_reasons(&candidates[2], &extensions).len(), 4);
+
+        let Err(Errors::NoSuitableDevice(message)) = selection.choose(&candidates, &extensions) else {
+            panic!("expected no suitable device");
+        };
+        assert_eq!(message.lines().count(), candidates.len());
+        assert!(message.contains("not the requested device (index 3)"), "{message}");
+    }
+}
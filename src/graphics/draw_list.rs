@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use glam::Mat4;
+
+use super::{camera::Camera, compute::ComputePass, material::Material, mesh::Mesh};
+
+/// A rectangle on the render target in pixels, with the origin at the top left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScreenRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> ScreenRect {
+        ScreenRect { x, y, width, height }
+    }
+
+    /// The area covered by both rectangles; empty if they don't overlap.
+    pub fn intersection(&self, other: &ScreenRect) -> ScreenRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+
+        ScreenRect { x, y, width: right.saturating_sub(x), height: bottom.saturating_sub(y) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// One mesh to draw this frame.
+#[derive(Clone)]
+pub struct DrawCommand {
+    pub mesh: Arc<Mesh>,
+    /// Model matrix, passed to the vertex shader as a push constant.
+    pub transform: Mat4,
+    /// The renderer's default vertex colored material when unset.
+    pub material: Option<Arc<Material>>,
+    /// Camera set when the command was submitted.
+    pub camera: Option<Camera>,
+    /// Area the mesh is projected into; the whole target when unset.
+    pub viewport: Option<ScreenRect>,
+    /// Pixels outside this area are discarded; nothing is clipped when unset.
+    pub scissor: Option<ScreenRect>,
+}
+
+/// Everything to draw in a frame, in submission order.
+///
+/// The application clears it before `Game::on_render`, so games submit the
+/// whole scene every frame. The camera, viewport and scissor rect apply to
+/// the commands submitted after they are set, so one list can hold several
+/// views, e.g. for split-screen or clipped UI panels. Compute passes run
+/// before or after all draw commands, as their stage says.
+#[derive(Clone, Default)]
+pub struct DrawList {
+    commands: Vec<DrawCommand>,
+    compute_passes: Vec<ComputePass>,
+    camera: Option<Camera>,
+    viewport: Option<ScreenRect>,
+    scissor: Option<ScreenRect>,
+}
+
+impl DrawList {
+    pub fn new() -> DrawList {
+        DrawList::default()
+    }
+
+    pub fn draw(&mut self, mesh: &Arc<Mesh>) {
+        self.draw_with_transform(mesh, Mat4::IDENTITY);
+    }
+
+    pub fn draw_with_transform(&mut self, mesh: &Arc<Mesh>, transform: Mat4) {
+        self.push(mesh, transform, None);
+    }
+
+    pub fn draw_with_material(&mut self, mesh: &Arc<Mesh>, transform: Mat4, material: &Arc<Material>) {
+        self.push(mesh, transform, Some(material.clone()));
+    }
+
+    /// Sets the camera the following commands are viewed through. Without
+    /// one, vertex positions are taken as clip-space coordinates. Kept by `clear`.
+    pub fn set_camera(&mut self, camera: Option<Camera>) {
+        self.camera = camera;
+    }
+
+    pub fn camera(&self) -> Option<&Camera> {
+        self.camera.as_ref()
+    }
+
+    /// Restricts the following commands to `viewport`, whose aspect ratio is
+    /// also used for the camera projection. Reset by `clear`.
+    pub fn set_viewport(&mut self, viewport: Option<ScreenRect>) {
+        self.viewport = viewport;
+    }
+
+    pub fn viewport(&self) -> Option<ScreenRect> {
+        self.viewport
+    }
+
+    /// Clips the following commands to `scissor`. Reset by `clear`.
+    pub fn set_scissor(&mut self, scissor: Option<ScreenRect>) {
+        self.scissor = scissor;
+    }
+
+    pub fn scissor(&self) -> Option<ScreenRect> {
+        self.scissor
+    }
+
+    /// Runs `pass` this frame, after the passes dispatched before it.
+    pub fn dispatch(&mut self, pass: &ComputePass) {
+        self.compute_passes.push(pass.clone());
+    }
+
+    pub fn compute_passes(&self) -> &[ComputePass] {
+        &self.compute_passes
+    }
+
+    /// Removes every draw command and compute pass and resets the viewport
+    /// and scissor rect.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.compute_passes.clear();
+        self.viewport = None;
+        self.scissor = None;
+    }
+
+    pub fn commands(&self) -> &[DrawCommand] {
+        &self.commands
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    fn push(&mut self, mesh: &Arc<Mesh>, transform: Mat4, material: Option<Arc<Material>>) {
+        self.commands.push(DrawCommand {
+            mesh: mesh.clone(),
+            transform,
+            material,
+            camera: self.camera,
+            viewport: self.viewport,
+            scissor: self.scissor,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_intersection_clips_to_overlap() {
+        let panel = ScreenRect::new(10, 10, 100, 50);
+
+        assert_eq!(panel.intersection(&ScreenRect::new(0, 0, 40, 40)), ScreenRect::new(10, 10, 30, 30));
+        assert_eq!(panel.intersection(&ScreenRect::new(0, 0, 800, 600)), panel);
+        assert!(panel.intersection(&ScreenRect::new(200, 0, 10, 10)).is_empty());
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use vulkano::{
+    command_buffer::{allocator::StandardCommandBufferAllocator, PrimaryAutoCommandBuffer},
+    swapchain::{self, AcquireError, Swapchain, SwapchainAcquireFuture, SwapchainPresentInfo},
+    sync::{self, future::FenceSignalFuture, FlushError, GpuFuture},
+    DeviceSize,
+};
+
+use crate::errors::Errors;
+
+use super::{
+    device_manager::DeviceManager,
+    gpu_resources::{GpuResources, UploadBuffer},
+};
+
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Bytes each frame in flight can upload per frame, e.g. for camera uniforms.
+pub const UPLOAD_BUFFER_SIZE: DeviceSize = 1 << 20;
+
+/// Allocators owned by one frame in flight. They are only reused once the GPU
+/// finished the frame that last used them.
+pub struct FrameResources {
+    pub command_buffer_allocator: StandardCommandBufferAllocator,
+    /// The frame's region of the upload ring, holding its camera uniforms.
+    pub upload_buffer: UploadBuffer,
+}
+
+impl FrameResources {
+    pub fn new(gpu_resources: &GpuResources, upload_buffer: UploadBuffer) -> FrameResources {
+        FrameResources {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(gpu_resources.device().clone(), Default::default()),
+            upload_buffer,
+        }
+    }
+}
+
+/// Time the CPU spent blocked waiting for the GPU or the presentation engine.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Frames started so far.
+    pub frame_count: u64,
+    pub last_cpu_wait: Duration,
+    pub total_cpu_wait: Duration,
+}
+
+impl FrameStats {
+    pub fn record_wait(&mut self, wait: Duration) {
+        self.frame_count += 1;
+        self.last_cpu_wait = wait;
+        self.total_cpu_wait += wait;
+    }
+
+    pub fn average_cpu_wait(&self) -> Duration {
+        if self.frame_count == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_nanos((self.total_cpu_wait.as_nanos() / self.frame_count as u128) as u64)
+    }
+}
+
+/// A frame between `FrameManager::acquire` and `FrameManager::present`.
+pub struct Frame {
+    /// Which of the frames in flight this is, in `0..frames_in_flight`.
+    pub index: usize,
+    /// The swapchain image to draw into.
+    pub image_index: u32,
+    acquire_future: SwapchainAcquireFuture,
+}
+
+type FrameFence = Arc<FenceSignalFuture<Box<dyn GpuFuture>>>;
+
+/// Limits how many frames the CPU records ahead of the GPU, independently of
+/// the number of swapchain images, and gives each of them its own resources.
+pub struct FrameManager {
+    resources: Vec<FrameResources>,
+    fences: Vec<Option<FrameFence>>,
+    current_frame: usize,
+    previous_frame: Option<usize>,
+    recreate_swapchain: bool,
+    stats: FrameStats,
+}
+
+impl FrameManager {
+    /// `frames_in_flight` is raised to at least one.
+    pub fn new(gpu_resources: &GpuResources, frames_in_flight: usize) -> Result<FrameManager, Errors> {
+        let frames_in_flight = frames_in_flight.max(1);
+        let upload_buffers = gpu_resources.upload_ring(UPLOAD_BUFFER_SIZE, frames_in_flight)?;
+
+        Ok(FrameManager {
+            resources: upload_buffers
+                .into_iter()
+                .map(|upload_buffer| FrameResources::new(gpu_resources, upload_buffer))
+                .collect(),
+            fences: vec![None; frames_in_flight],
+            current_frame: 0,
+            previous_frame: None,
+            recreate_swapchain: false,
+            stats: FrameStats::default(),
+        })
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.resources.len()
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    pub fn resources(&self, frame: &Frame) -> &FrameResources {
+        &self.resources[frame.index]
+    }
+
+    /// Whether acquiring or presenting found the swapchain out of date or
+    /// suboptimal since the last call.
+    pub fn take_recreate_request(&mut self) -> bool {
+        std::mem::take(&mut self.recreate_swapchain)
+    }
+
+    /// Waits until the GPU finished the frame that last used the next frame's
+    /// resources, then acquires a swapchain image. Returns `None` if the
+    /// swapchain has to be recreated first.
+    pub fn acquire(&mut self, swapchain: &Arc<Swapchain>) -> Result<Option<Frame>, Errors> {
+        let index = self.current_frame;
+        let wait_start = Instant::now();
+        if let Some(fence) = self.fences[index].take() {
+            fence.wait(None)?;
+        }
+        self.resources[index].upload_buffer.reset();
+
+        let acquired = swapchain::acquire_next_image(swapchain.clone(), None);
+        self.stats.record_wait(wait_start.elapsed());
+
+        let (image_index, suboptimal, acquire_future) = match acquired {
+            Ok(acquired) => acquired,
+            Err(AcquireError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                return Ok(None);
+            }
+            Err(err) => return Err(err.into()),
+        };
+        self.recreate_swapchain |= suboptimal;
+
+        Ok(Some(Frame { index, image_index, acquire_future }))
+    }
+
+    /// Submits `command_buffer` for `frame` and presents its image. The
+    /// submission waits for `submitted`, e.g. from `Renderer::take_submitted`.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn present(
+        &mut self,
+        device_manager: &DeviceManager,
+        swapchain: &Arc<Swapchain>,
+        frame: Frame,
+        command_buffer: PrimaryAutoCommandBuffer,
+        submitted: Option<Box<dyn GpuFuture>>,
+    ) -> Result<(), Errors> {
+        // Chaining onto the previous frame lets it keep using resources it
+        // shares with this one until it has finished.
+        let previous_future = match self.previous_frame.and_then(|previous| self.fences[previous].clone()) {
+            Some(fence) => fence.boxed(),
+            None => sync::now(device_manager.device.clone()).boxed(),
+        };
+
+        let previous_future = match submitted {
+            Some(submitted) => previous_future.join(submitted).boxed(),
+            None => previous_future,
+        };
+
+        let future = previous_future
+            .join(frame.acquire_future)
+            .then_execute(device_manager.queue.clone(), command_buffer)?
+            .then_swapchain_present(
+                device_manager.queue.clone(),
+                SwapchainPresentInfo::swapchain_image_index(swapchain.clone(), frame.image_index),
+            )
+            .boxed()
+            .then_signal_fence_and_flush();
+
+        self.fences[frame.index] = match future {
+            Ok(fence) => Some(Arc::new(fence)),
+            Err(FlushError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                None
+            }
+            Err(FlushError::DeviceLost) => return Err(Errors::DeviceLost),
+            Err(err) => {
+                warn!("failed to flush frame {}: {err}", frame.index);
+                None
+            }
+        };
+        self.previous_frame = Some(frame.index);
+        self.current_frame = (frame.index + 1) % self.frames_in_flight();
+
+        Ok(())
+    }
+
+    /// Whether the last `present` of the frame at `frame_index` was submitted
+    /// to the GPU; `false` when flushing it failed.
+    pub fn is_submitted(&self, frame_index: usize) -> bool {
+        self.fences[frame_index].is_some()
+    }
+
+    /// Blocks until every submitted frame has finished.
+    pub fn wait_idle(&mut self) -> Result<(), Errors> {
+        for fence in self.fences.iter_mut().filter_map(Option::take) {
+            fence.wait(None)?;
+        }
+        self.previous_frame = None;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_average_cpu_wait() {
+        let mut stats = FrameStats::default();
+        assert_eq!(stats.average_cpu_wait(), Duration::ZERO);
+
+        stats.record_wait(Duration::from_millis(4));
+        stats.record_wait(Duration::from_millis(2));
+        assert_eq!(stats.frame_count, 2);
+        assert_eq!(stats.last_cpu_wait, Duration::from_millis(2));
+        assert_eq!(stats.total_cpu_wait, Duration::from_millis(6));
+        assert_eq!(stats.average_cpu_wait(), Duration::from_millis(3));
+    }
+}
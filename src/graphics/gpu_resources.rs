@@ -0,0 +1,427 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferMemory, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferInfo,
+        PrimaryAutoCommandBuffer,
+    },
+    descriptor_set::allocator::StandardDescriptorSetAllocator,
+    device::Device,
+    format::Format,
+    image::{
+        sys::{Image, ImageMemory},
+        view::ImageView,
+        ImageAccess, ImageCreateFlags, ImageDimensions, ImageUsage, StorageImage,
+    },
+    memory::{
+        allocator::{AllocationCreateInfo, MemoryAlloc, MemoryUsage, StandardMemoryAllocator},
+        MemoryHeapFlags,
+    },
+    sync::{self, GpuFuture, Sharing},
+    DeviceSize,
+};
+
+use crate::errors::Errors;
+
+use super::{debug, device_manager::DeviceManager};
+
+/// Memory use of one device heap, counting resources created through
+/// `GpuResources` that are still alive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapStats {
+    pub heap_index: u32,
+    /// Capacity of the heap in bytes.
+    pub size: DeviceSize,
+    /// Whether the heap is video memory rather than system memory.
+    pub device_local: bool,
+    pub allocated: DeviceSize,
+    pub allocation_count: usize,
+}
+
+struct TrackedAllocation {
+    heap_index: u32,
+    size: DeviceSize,
+    /// The buffer or image the memory is bound to; the memory is freed with it.
+    owner: Weak<dyn Send + Sync>,
+}
+
+/// Allocators shared by everything creating GPU resources, with helpers for
+/// common buffer uploads.
+///
+/// Resources created here or passed to `track_buffer` and `track_image` are
+/// counted in `memory_stats` until they are dropped.
+pub struct GpuResources {
+    device_manager: Arc<DeviceManager>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    /// Records one-off transfers, such as staging copies.
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    allocations: Mutex<Vec<TrackedAllocation>>,
+}
+
+impl GpuResources {
+    pub fn new(device_manager: &Arc<DeviceManager>) -> Arc<GpuResources> {
+        let device = device_manager.device.clone();
+
+        Arc::new(GpuResources {
+            device_manager: device_manager.clone(),
+            memory_allocator: Arc::new(StandardMemoryAllocator::new_default(device.clone())),
+            command_buffer_allocator: StandardCommandBufferAllocator::new(device.clone(), Default::default()),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(device),
+            allocations: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn device_manager(&self) -> &Arc<DeviceManager> {
+        &self.device_manager
+    }
+
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device_manager.device
+    }
+
+    pub fn memory_allocator(&self) -> &Arc<StandardMemoryAllocator> {
+        &self.memory_allocator
+    }
+
+    pub fn command_buffer_allocator(&self) -> &StandardCommandBufferAllocator {
+        &self.command_buffer_allocator
+    }
+
+    pub fn descriptor_set_allocator(&self) -> &StandardDescriptorSetAllocator {
+        &self.descriptor_set_allocator
+    }
+
+    /// Records commands with `record`, submits them to the graphics queue and
+    /// waits until they finished.
+    pub fn execute_and_wait<T>(
+        &self,
+        record: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<T, Errors>,
+    ) -> Result<T, Errors> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.device_manager.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        let result = record(&mut builder)?;
+
+        sync::now(self.device().clone())
+            .then_execute(self.device_manager.queue.clone(), builder.build()?)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+
+        Ok(result)
+    }
+
+    /// A buffer in host-visible memory the CPU can keep writing to, e.g. for
+    /// data that changes every frame.
+    pub fn host_buffer_from_data<T: BufferContents>(
+        &self,
+        usage: BufferUsage,
+        data: T,
+        name: &str,
+    ) -> Result<Subbuffer<T>, Errors> {
+        let buffer = Buffer::from_data(
+            self.memory_allocator.as_ref(),
+            BufferCreateInfo { usage, ..Default::default() },
+            AllocationCreateInfo { usage: MemoryUsage::Upload, ..Default::default() },
+            data,
+        )?;
+        self.name_and_track(&buffer, name);
+
+        Ok(buffer)
+    }
+
+    pub fn host_buffer_from_iter<T: BufferContents>(
+        &self,
+        usage: BufferUsage,
+        data: impl ExactSizeIterator<Item = T>,
+        name: &str,
+    ) -> Result<Subbuffer<[T]>, Errors> {
+        let buffer = Buffer::from_iter(
+            self.memory_allocator.as_ref(),
+            BufferCreateInfo { usage, ..Default::default() },
+            AllocationCreateInfo { usage: MemoryUsage::Upload, ..Default::default() },
+            data,
+        )?;
+        self.name_and_track(&buffer, name);
+
+        Ok(buffer)
+    }
+
+    /// A buffer in device-local memory, filled through a staging buffer.
+    /// Waits for the copy, so the result can be used right away.
+    pub fn device_local_buffer_from_iter<T: BufferContents>(
+        &self,
+        usage: BufferUsage,
+        data: impl ExactSizeIterator<Item = T>,
+        name: &str,
+    ) -> Result<Subbuffer<[T]>, Errors> {
+        self.execute_and_wait(|builder| self.record_device_local_buffer_from_iter(builder, usage, data, name))
+    }
+
+    /// Like `device_local_buffer_from_iter`, but only records the copy into
+    /// `builder`, which may be for any queue. The buffer can be used once the
+    /// command buffer has executed.
+    pub fn record_device_local_buffer_from_iter<T: BufferContents>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        usage: BufferUsage,
+        data: impl ExactSizeIterator<Item = T>,
+        name: &str,
+    ) -> Result<Subbuffer<[T]>, Errors> {
+        let staging_buffer = Buffer::from_iter(
+            self.memory_allocator.as_ref(),
+            BufferCreateInfo { usage: BufferUsage::TRANSFER_SRC, ..Default::default() },
+            AllocationCreateInfo { usage: MemoryUsage::Upload, ..Default::default() },
+            data,
+        )?;
+        // Shared by all queue families, so a buffer copied on the transfer
+        // queue can be drawn on the graphics queue without an ownership transfer.
+        let sharing = match self.device().active_queue_family_indices() {
+            [_] => Sharing::Exclusive,
+            queue_families => Sharing::Concurrent(queue_families.iter().copied().collect()),
+        };
+        let buffer = Buffer::new_slice::<T>(
+            self.memory_allocator.as_ref(),
+            BufferCreateInfo { sharing, usage: usage | BufferUsage::TRANSFER_DST, ..Default::default() },
+            AllocationCreateInfo { usage: MemoryUsage::DeviceOnly, ..Default::default() },
+            staging_buffer.len(),
+        )?;
+        self.name_and_track(&buffer, name);
+        builder.copy_buffer(CopyBufferInfo::buffers(staging_buffer, buffer.clone()))?;
+
+        Ok(buffer)
+    }
+
+    /// A host-visible buffer of `len` bytes for copying GPU results into.
+    pub fn readback_buffer(&self, len: DeviceSize, name: &str) -> Result<Subbuffer<[u8]>, Errors> {
+        let buffer = Buffer::new_slice::<u8>(
+            self.memory_allocator.as_ref(),
+            BufferCreateInfo { usage: BufferUsage::TRANSFER_DST, ..Default::default() },
+            AllocationCreateInfo { usage: MemoryUsage::Download, ..Default::default() },
+            len,
+        )?;
+        self.name_and_track(&buffer, name);
+
+        Ok(buffer)
+    }
+
+    /// A 2D image compute shaders can write and later passes can sample or
+    /// copy from.
+    pub fn storage_image(
+        &self,
+        dimensions: [u32; 2],
+        format: Format,
+        name: &str,
+    ) -> Result<Arc<ImageView<StorageImage>>, Errors> {
+        let image = StorageImage::with_usage(
+            self.memory_allocator.as_ref(),
+            ImageDimensions::Dim2d { width: dimensions[0], height: dimensions[1], array_layers: 1 },
+            format,
+            ImageUsage::STORAGE | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST,
+            ImageCreateFlags::empty(),
+            self.device().active_queue_family_indices().iter().copied(),
+        )?;
+        debug::set_object_name(image.inner().image.as_ref(), name);
+        self.track_image(image.inner().image);
+
+        Ok(ImageView::new_default(image)?)
+    }
+
+    /// One host-visible buffer split into `regions` upload buffers of at least
+    /// `region_size` bytes, one for each frame in flight.
+    pub fn upload_ring(&self, region_size: DeviceSize, regions: usize) -> Result<Vec<UploadBuffer>, Errors> {
+        let properties = self.device_manager.physical_device.properties();
+        let alignment = [
+            properties.min_uniform_buffer_offset_alignment,
+            properties.min_storage_buffer_offset_alignment,
+            properties.non_coherent_atom_size,
+        ]
+        .into_iter()
+        .map(|alignment| alignment.as_devicesize())
+        .max()
+        .unwrap_or(1);
+        let region_size = region_size.max(1).next_multiple_of(alignment);
+
+        let buffer = Buffer::new_slice::<u8>(
+            self.memory_allocator.as_ref(),
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER
+                    | BufferUsage::STORAGE_BUFFER
+                    | BufferUsage::VERTEX_BUFFER
+                    | BufferUsage::INDEX_BUFFER
+                    | BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo { usage: MemoryUsage::Upload, ..Default::default() },
+            region_size * regions.max(1) as DeviceSize,
+        )?;
+        self.name_and_track(&buffer, "upload ring");
+
+        Ok((0..regions.max(1) as DeviceSize)
+            .map(|region| UploadBuffer {
+                buffer: buffer.clone().slice(region * region_size..(region + 1) * region_size),
+                alignment,
+                cursor: Mutex::new(0),
+            })
+            .collect())
+    }
+
+    /// Counts `buffer` in `memory_stats`, for buffers allocated elsewhere.
+    pub fn track_buffer(&self, buffer: &Arc<Buffer>) {
+        if let BufferMemory::Normal(allocation) = buffer.memory() {
+            let owner: Weak<Buffer> = Arc::downgrade(buffer);
+            self.track(owner, [allocation]);
+        }
+    }
+
+    /// Counts `image` in `memory_stats`. Images are created by vulkano's image
+    /// types from `memory_allocator`, so callers track them themselves.
+    pub fn track_image(&self, image: &Arc<Image>) {
+        if let ImageMemory::Normal(allocations) = image.memory() {
+            let owner: Weak<Image> = Arc::downgrade(image);
+            self.track(owner, allocations);
+        }
+    }
+
+    /// Memory allocated in each device heap by tracked resources still alive.
+    pub fn memory_stats(&self) -> Vec<HeapStats> {
+        let memory_properties = self.device_manager.physical_device.memory_properties();
+        let mut stats: Vec<HeapStats> = memory_properties
+            .memory_heaps
+            .iter()
+            .enumerate()
+            .map(|(heap_index, heap)| HeapStats {
+                heap_index: heap_index as u32,
+                size: heap.size,
+                device_local: heap.flags.intersects(MemoryHeapFlags::DEVICE_LOCAL),
+                allocated: 0,
+                allocation_count: 0,
+            })
+            .collect();
+
+        let mut allocations = self.allocations.lock().unwrap();
+        allocations.retain(|allocation| allocation.owner.strong_count() > 0);
+        add_allocations(&mut stats, &allocations);
+
+        stats
+    }
+
+    fn name_and_track<T: ?Sized>(&self, buffer: &Subbuffer<T>, name: &str) {
+        debug::set_object_name(buffer.buffer().as_ref(), name);
+        self.track_buffer(buffer.buffer());
+    }
+
+    fn track<'a>(&self, owner: Weak<dyn Send + Sync>, allocations: impl IntoIterator<Item = &'a MemoryAlloc>) {
+        let memory_types = &self.device_manager.physical_device.memory_properties().memory_types;
+        let mut tracked = self.allocations.lock().unwrap();
+        for allocation in allocations {
+            let memory_type_index = allocation.device_memory().memory_type_index() as usize;
+            tracked.push(TrackedAllocation {
+                heap_index: memory_types[memory_type_index].heap_index,
+                size: allocation.size(),
+                owner: owner.clone(),
+            });
+        }
+    }
+}
+
+fn add_allocations(stats: &mut [HeapStats], allocations: &[TrackedAllocation]) {
+    for allocation in allocations {
+        if let Some(heap) = stats.get_mut(allocation.heap_index as usize) {
+            heap.allocated += allocation.size;
+            heap.allocation_count += 1;
+        }
+    }
+}
+
+/// A region of an upload ring that one frame in flight writes its transient
+/// data into, such as uniforms. Call `reset` once the GPU finished the frame
+/// that last used it.
+pub struct UploadBuffer {
+    buffer: Subbuffer<[u8]>,
+    alignment: DeviceSize,
+    cursor: Mutex<DeviceSize>,
+}
+
+impl UploadBuffer {
+    /// Capacity in bytes.
+    pub fn size(&self) -> DeviceSize {
+        self.buffer.size()
+    }
+
+    /// Bytes handed out since the last `reset`.
+    pub fn used(&self) -> DeviceSize {
+        *self.cursor.lock().unwrap()
+    }
+
+    pub fn reset(&self) {
+        *self.cursor.lock().unwrap() = 0;
+    }
+
+    /// Copies `data` into the next free, suitably aligned part of the region.
+    pub fn push<T: BufferContents>(&self, data: T) -> Result<Subbuffer<T>, Errors> {
+        let size = std::mem::size_of::<T>() as DeviceSize;
+        let alignment = self.alignment.max(std::mem::align_of::<T>() as DeviceSize);
+
+        let mut cursor = self.cursor.lock().unwrap();
+        let offset = next_offset(*cursor, size, alignment, self.size()).ok_or_else(|| {
+            Errors::Graphics(format!("per-frame upload buffer of {} bytes is full", self.size()))
+        })?;
+        *cursor = offset + size;
+
+        let bytes = self.buffer.clone().slice(offset..offset + size);
+        let subbuffer = Subbuffer::<T>::try_from_bytes(bytes)
+            .map_err(|err| Errors::Graphics(format!("misaligned upload: {err:?}")))?;
+        *subbuffer.write()? = data;
+
+        Ok(subbuffer)
+    }
+}
+
+/// Where `size` bytes aligned to `alignment` start when the region is filled
+/// up to `cursor`, or `None` if they don't fit into `capacity`.
+fn next_offset(cursor: DeviceSize, size: DeviceSize, alignment: DeviceSize, capacity: DeviceSize) -> Option<DeviceSize> {
+    let offset = cursor.next_multiple_of(alignment);
+
+    (size > 0 && offset.checked_add(size)? <= capacity).then_some(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uploads_are_aligned_and_bounded() {
+        assert_eq!(next_offset(0, 64, 256, 1024), Some(0));
+        assert_eq!(next_offset(64, 64, 256, 1024), Some(256));
+        assert_eq!(next_offset(256, 64, 256, 1024), Some(256));
+        assert_eq!(next_offset(768, 256, 256, 1024), Some(768));
+        assert_eq!(next_offset(769, 64, 256, 1024), None);
+        assert_eq!(next_offset(0, 0, 256, 1024), None);
+    }
+
+    #[test]
+    fn stats_count_allocations_per_heap() {
+        let owner: Arc<dyn Send + Sync> = Arc::new(());
+        let heap = |heap_index| HeapStats {
+            heap_index,
+            size: 1 << 30,
+            device_local: heap_index == 0,
+            allocated: 0,
+            allocation_count: 0,
+        };
+        let mut stats = vec![heap(0), heap(1)];
+        let allocations = [(0, 256), (1, 64), (0, 1024)].map(|(heap_index, size)| TrackedAllocation {
+            heap_index,
+            size,
+            owner: Arc::downgrade(&owner),
+        });
+
+        add_allocations(&mut stats, &allocations);
+        assert_eq!((stats[0].allocated, stats[0].allocation_count), (1280, 2));
+        assert_eq!((stats[1].allocated, stats[1].allocation_count), (64, 1));
+    }
+}
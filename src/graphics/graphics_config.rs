@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use winit::event::VirtualKeyCode;
+
+use super::{
+    device_selection::DeviceSelection, frame_manager, pipeline_cache, swapchain_manager::SwapchainConfig,
+};
+
+/// Graphics options an `Application` is created with.
+#[derive(Clone, Debug)]
+pub struct GraphicsConfig {
+    /// Where compiled pipelines are kept between launches; `None` keeps them
+    /// in memory only.
+    pub pipeline_cache_dir: Option<PathBuf>,
+    /// Enables the validation layer and routes its messages to the log.
+    pub vulkan_debug: bool,
+    pub swapchain: SwapchainConfig,
+    /// How many frames the CPU may record before waiting for the GPU.
+    pub frames_in_flight: usize,
+    pub device_selection: DeviceSelection,
+    /// Saves a screenshot when pressed; `None` disables the hotkey.
+    pub screenshot_key: Option<VirtualKeyCode>,
+    /// Where screenshots without an explicit path are saved.
+    pub screenshot_dir: PathBuf,
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        GraphicsConfig {
+            pipeline_cache_dir: Some(pipeline_cache::default_cache_dir()),
+            vulkan_debug: false,
+            swapchain: SwapchainConfig::default(),
+            frames_in_flight: frame_manager::DEFAULT_FRAMES_IN_FLIGHT,
+            device_selection: DeviceSelection::default(),
+            screenshot_key: Some(VirtualKeyCode::F12),
+            screenshot_dir: PathBuf::from("screenshots"),
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::sync::{Arc, Mutex};
+
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorSetLayout, DescriptorSet, PersistentDescriptorSet,
+        WriteDescriptorSet,
+    },
+    device::Device,
+    pipeline::graphics::{
+        color_blend::ColorBlendState,
+        rasterization::{CullMode, RasterizationState},
+    },
+};
+
+use crate::errors::Errors;
+
+use super::{depth::DepthConfig, gpu_resources::GpuResources, shader::Shader, shaders, texture::Texture};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Overwrites the target.
+    Opaque,
+    /// Blends by source alpha.
+    Alpha,
+    /// Adds source color to the target.
+    Additive,
+}
+
+/// Fixed-function state baked into a material's pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineState {
+    pub blend: BlendMode,
+    pub cull_mode: CullMode,
+    pub depth: DepthConfig,
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        PipelineState { blend: BlendMode::Opaque, cull_mode: CullMode::None, depth: DepthConfig::default() }
+    }
+}
+
+impl PipelineState {
+    pub fn color_blend_state(&self) -> ColorBlendState {
+        match self.blend {
+            BlendMode::Opaque => ColorBlendState::new(1),
+            BlendMode::Alpha => ColorBlendState::new(1).blend_alpha(),
+            BlendMode::Additive => ColorBlendState::new(1).blend_additive(),
+        }
+    }
+
+    pub fn rasterization_state(&self) -> RasterizationState {
+        RasterizationState::new().cull_mode(self.cull_mode)
+    }
+}
+
+/// Parameter block used by the built-in shaders.
+#[derive(BufferContents, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct StandardParameters {
+    /// Multiplied with the vertex color.
+    pub base_color: [f32; 4],
+}
+
+impl Default for StandardParameters {
+    fn default() -> Self {
+        StandardParameters { base_color: [1.0, 1.0, 1.0, 1.0] }
+    }
+}
+
+/// Shader modules shared by the built-in materials.
+pub struct StandardShaders {
+    pub vertex: Arc<Shader>,
+    /// Draws vertex colors times `base_color`.
+    pub color: Arc<Shader>,
+    /// Like `color`, also multiplied with the first texture.
+    pub textured: Arc<Shader>,
+}
+
+impl StandardShaders {
+    pub fn load(device: &Arc<Device>) -> Result<Arc<StandardShaders>, Errors> {
+        Ok(Arc::new(StandardShaders {
+            vertex: Shader::from_module(shaders::vertex::vertex_shader::load(device.clone())?),
+            color: Shader::from_module(shaders::fragment::fragment_shader::load(device.clone())?),
+            textured: Shader::from_module(shaders::fragment::textured_fragment_shader::load(device.clone())?),
+        }))
+    }
+}
+
+/// A shader pair with its pipeline state and resources.
+///
+/// Resources live in descriptor set 1: the parameter block at binding 0,
+/// then texture `i` at binding `1 + 2 * i` with its sampler at `2 + 2 * i`.
+/// Materials are immutable, so their descriptor set is created once and reused.
+pub struct Material {
+    vertex_shader: Arc<Shader>,
+    fragment_shader: Arc<Shader>,
+    state: PipelineState,
+    parameters: Subbuffer<[u8]>,
+    textures: Vec<Arc<Texture>>,
+    descriptor_set: Mutex<Option<Arc<PersistentDescriptorSet>>>,
+}
+
+impl Material {
+    pub fn vertex_shader(&self) -> &Arc<Shader> {
+        &self.vertex_shader
+    }
+
+    pub fn fragment_shader(&self) -> &Arc<Shader> {
+        &self.fragment_shader
+    }
+
+    pub fn state(&self) -> PipelineState {
+        self.state
+    }
+
+    pub fn textures(&self) -> &[Arc<Texture>] {
+        &self.textures
+    }
+
+    /// The material's descriptor set for `layout`, created on first use.
+    pub fn descriptor_set(
+        &self,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        layout: &Arc<DescriptorSetLayout>,
+    ) -> Result<Arc<PersistentDescriptorSet>, Errors> {
+        let mut descriptor_set = self.descriptor_set.lock().unwrap();
+        if let Some(descriptor_set) = descriptor_set.as_ref() {
+            // Pipelines built from the same shaders have equal set layouts, so
+            // one set serves all of them.
+            if descriptor_set.layout().is_compatible_with(layout) {
+                return Ok(descriptor_set.clone());
+            }
+        }
+
+        let mut writes = vec![WriteDescriptorSet::buffer(0, self.parameters.clone())];
+        for (i, texture) in self.textures.iter().enumerate() {
+            let binding = 1 + 2 * i as u32;
+            writes.push(WriteDescriptorSet::image_view(binding, texture.view.clone()));
+            writes.push(WriteDescriptorSet::sampler(binding + 1, texture.sampler.clone()));
+        }
+
+        let new_descriptor_set = PersistentDescriptorSet::new(descriptor_set_allocator, layout.clone(), writes)?;
+        *descriptor_set = Some(new_descriptor_set.clone());
+
+        Ok(new_descriptor_set)
+    }
+}
+
+/// Configures and creates a `Material`.
+pub struct MaterialBuilder {
+    gpu_resources: Arc<GpuResources>,
+    vertex_shader: Arc<Shader>,
+    fragment_shader: Arc<Shader>,
+    state: PipelineState,
+    parameters: Result<Subbuffer<[u8]>, Errors>,
+    textures: Vec<Arc<Texture>>,
+}
+
+impl MaterialBuilder {
+    pub fn new(
+        gpu_resources: Arc<GpuResources>,
+        vertex_shader: Arc<Shader>,
+        fragment_shader: Arc<Shader>,
+    ) -> MaterialBuilder {
+        MaterialBuilder {
+            parameters: Self::parameter_buffer(&gpu_resources, StandardParameters::default()),
+            gpu_resources,
+            vertex_shader,
+            fragment_shader,
+            state: PipelineState::default(),
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn with_state(mut self, state: PipelineState) -> MaterialBuilder {
+        self.state = state;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> MaterialBuilder {
+        self.state.blend = blend;
+        self
+    }
+
+    pub fn with_cull_mode(mut self, cull_mode: CullMode) -> MaterialBuilder {
+        self.state.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: DepthConfig) -> MaterialBuilder {
+        self.state.depth = depth;
+        self
+    }
+
+    /// Sets the parameter block; `StandardParameters` for the built-in shaders.
+    pub fn with_parameters<T: BufferContents>(mut self, parameters: T) -> MaterialBuilder {
+        self.parameters = Self::parameter_buffer(&self.gpu_resources, parameters);
+        self
+    }
+
+    pub fn with_texture(mut self, texture: &Arc<Texture>) -> MaterialBuilder {
+        self.textures.push(texture.clone());
+        self
+    }
+
+    pub fn build(self) -> Result<Arc<Material>, Errors> {
+        Ok(Arc::new(Material {
+            vertex_shader: self.vertex_shader,
+            fragment_shader: self.fragment_shader,
+            state: self.state,
+            parameters: self.parameters?,
+            textures: self.textures,
+            descriptor_set: Mutex::new(None),
+        }))
+    }
+
+    fn parameter_buffer<T: BufferContents>(
+        gpu_resources: &GpuResources,
+        parameters: T,
+    ) -> Result<Subbuffer<[u8]>, Errors> {
+        let buffer = gpu_resources.host_buffer_from_data(BufferUsage::UNIFORM_BUFFER, parameters, "material parameters")?;
+
+        Ok(buffer.into_bytes())
+    }
+}
@@ -0,0 +1,300 @@
+use std::sync::Arc;
+
+use vulkano::buffer::{BufferContents, BufferUsage, Subbuffer};
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+
+use crate::errors::Errors;
+
+use super::gpu_resources::GpuResources;
+
+/// Vertex layout shared by every mesh.
+#[derive(BufferContents, Vertex, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct MeshVertex {
+    #[format(R32G32B32_SFLOAT)]
+    pub position: [f32; 3],
+    #[format(R32G32B32_SFLOAT)]
+    pub normal: [f32; 3],
+    #[format(R32G32_SFLOAT)]
+    pub uv: [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color: [f32; 4],
+    /// Tangent direction, with the bitangent sign in `w`.
+    #[format(R32G32B32A32_SFLOAT)]
+    pub tangent: [f32; 4],
+}
+
+impl MeshVertex {
+    /// A white vertex facing +Z with no texture coordinates.
+    pub fn new(position: [f32; 3]) -> MeshVertex {
+        MeshVertex {
+            position,
+            normal: [0.0, 0.0, 1.0],
+            uv: [0.0, 0.0],
+            color: [1.0, 1.0, 1.0, 1.0],
+            tangent: [1.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    pub fn with_uv(mut self, uv: [f32; 2]) -> MeshVertex {
+        self.uv = uv;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> MeshVertex {
+        self.color = color;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn max(&self) -> Option<u32> {
+        match self {
+            Indices::U16(indices) => indices.iter().max().map(|index| *index as u32),
+            Indices::U32(indices) => indices.iter().max().copied(),
+        }
+    }
+}
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    /// Smallest box containing every point, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Option<Aabb> {
+        points.into_iter().fold(None, |aabb, point| {
+            let Aabb { mut min, mut max } = aabb.unwrap_or(Aabb { min: point, max: point });
+            for axis in 0..3 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+
+            Some(Aabb { min, max })
+        })
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        [0, 1, 2].map(|axis| (self.min[axis] + self.max[axis]) * 0.5)
+    }
+
+    pub fn size(&self) -> [f32; 3] {
+        [0, 1, 2].map(|axis| self.max[axis] - self.min[axis])
+    }
+}
+
+/// Mesh geometry on the CPU, ready to be uploaded with `Mesh::new`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Option<Indices>,
+}
+
+impl MeshData {
+    pub fn new(vertices: Vec<MeshVertex>, indices: Option<Indices>) -> MeshData {
+        MeshData { vertices, indices }
+    }
+
+    /// A square of side 1 centered on the origin in the XY plane, drawn with
+    /// four vertices and six indices.
+    pub fn quad() -> MeshData {
+        let vertices = vec![
+            MeshVertex::new([-0.5, -0.5, 0.0]).with_uv([0.0, 0.0]),
+            MeshVertex::new([0.5, -0.5, 0.0]).with_uv([1.0, 0.0]),
+            MeshVertex::new([0.5, 0.5, 0.0]).with_uv([1.0, 1.0]),
+            MeshVertex::new([-0.5, 0.5, 0.0]).with_uv([0.0, 1.0]),
+        ];
+
+        MeshData::new(vertices, Some(Indices::U16(vec![0, 1, 2, 2, 3, 0])))
+    }
+
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|vertex| vertex.position))
+    }
+
+    fn validate(&self) -> Result<(), Errors> {
+        if self.vertices.is_empty() {
+            return Err(Errors::Graphics("mesh has no vertices".to_string()));
+        }
+        if let Some(max_index) = self.indices.as_ref().and_then(Indices::max) {
+            if max_index as usize >= self.vertices.len() {
+                return Err(Errors::Graphics(format!(
+                    "mesh index {max_index} is out of range for {} vertices",
+                    self.vertices.len()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+enum IndexBuffer {
+    U16(Subbuffer<[u16]>),
+    U32(Subbuffer<[u32]>),
+}
+
+/// Mesh geometry uploaded to the GPU.
+pub struct Mesh {
+    vertex_buffer: Subbuffer<[MeshVertex]>,
+    index_buffer: Option<IndexBuffer>,
+    bounding_box: Aabb,
+}
+
+impl Mesh {
+    /// Copies the geometry into device-local buffers and waits for the upload.
+    pub fn new(gpu_resources: &GpuResources, data: &MeshData) -> Result<Arc<Mesh>, Errors> {
+        gpu_resources.execute_and_wait(|builder| Self::record_upload(gpu_resources, data, builder))
+    }
+
+    /// Records the copies into device-local buffers into `builder`. The mesh
+    /// can be drawn once the command buffer has executed.
+    pub fn record_upload(
+        gpu_resources: &GpuResources,
+        data: &MeshData,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<Arc<Mesh>, Errors> {
+        data.validate()?;
+
+        let vertex_buffer = Self::upload(
+            gpu_resources,
+            builder,
+            BufferUsage::VERTEX_BUFFER | BufferUsage::STORAGE_BUFFER,
+            data.vertices.iter().copied(),
+            "mesh vertices",
+        )?;
+        let index_buffer = match &data.indices {
+            None => None,
+            Some(Indices::U16(indices)) => Some(IndexBuffer::U16(Self::upload(
+                gpu_resources,
+                builder,
+                BufferUsage::INDEX_BUFFER,
+                indices.iter().copied(),
+                "mesh indices",
+            )?)),
+            Some(Indices::U32(indices)) => Some(IndexBuffer::U32(Self::upload(
+                gpu_resources,
+                builder,
+                BufferUsage::INDEX_BUFFER,
+                indices.iter().copied(),
+                "mesh indices",
+            )?)),
+        };
+        let bounding_box = data.bounding_box().ok_or_else(|| Errors::Graphics("mesh has no vertices".to_string()))?;
+
+        Ok(Arc::new(Mesh { vertex_buffer, index_buffer, bounding_box }))
+    }
+
+    /// The vertices, also bindable as a storage buffer, e.g. for compute
+    /// passes that animate the mesh.
+    pub fn vertex_buffer(&self) -> &Subbuffer<[MeshVertex]> {
+        &self.vertex_buffer
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_buffer.len() as u32
+    }
+
+    pub fn index_count(&self) -> Option<u32> {
+        self.index_buffer.as_ref().map(|index_buffer| match index_buffer {
+            IndexBuffer::U16(indices) => indices.len() as u32,
+            IndexBuffer::U32(indices) => indices.len() as u32,
+        })
+    }
+
+    /// Records the draw into `builder`, which must already have a pipeline
+    /// using the `MeshVertex` layout bound.
+    pub fn draw<L, A: CommandBufferAllocator>(&self, builder: &mut AutoCommandBufferBuilder<L, A>) -> Result<(), Errors> {
+        builder.bind_vertex_buffers(0, self.vertex_buffer.clone());
+
+        match &self.index_buffer {
+            None => {
+                builder.draw(self.vertex_count(), 1, 0, 0)?;
+            }
+            Some(IndexBuffer::U16(indices)) => {
+                builder
+                    .bind_index_buffer(indices.clone())
+                    .draw_indexed(indices.len() as u32, 1, 0, 0, 0)?;
+            }
+            Some(IndexBuffer::U32(indices)) => {
+                builder
+                    .bind_index_buffer(indices.clone())
+                    .draw_indexed(indices.len() as u32, 1, 0, 0, 0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn upload<T: BufferContents>(
+        gpu_resources: &GpuResources,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        usage: BufferUsage,
+        data: impl ExactSizeIterator<Item = T>,
+        name: &str,
+    ) -> Result<Subbuffer<[T]>, Errors> {
+        if data.len() == 0 {
+            return Err(Errors::Graphics(format!("{name} are empty")));
+        }
+
+        gpu_resources.record_device_local_buffer_from_iter(builder, usage, data, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounding_box_spans_all_vertices() {
+        let data = MeshData::new(
+            vec![
+                MeshVertex::new([-1.0, 2.0, 0.5]),
+                MeshVertex::new([3.0, -4.0, 0.0]),
+                MeshVertex::new([0.0, 0.0, -2.0]),
+            ],
+            None,
+        );
+
+        let aabb = data.bounding_box().unwrap();
+        assert_eq!(aabb, Aabb { min: [-1.0, -4.0, -2.0], max: [3.0, 2.0, 0.5] });
+        assert_eq!(aabb.center(), [1.0, -1.0, -0.75]);
+        assert_eq!(aabb.size(), [4.0, 6.0, 2.5]);
+        assert_eq!(MeshData::new(Vec::new(), None).bounding_box(), None);
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        let mut data = MeshData::quad();
+        assert!(data.validate().is_ok());
+
+        data.indices = Some(Indices::U32(vec![0, 1, 4]));
+        assert!(matches!(data.validate(), Err(Errors::Graphics(_))));
+    }
+}
@@ -3,4 +3,22 @@ pub mod device_manager;
 pub mod vulkan_instance;
 pub mod swapchain_manager;
 pub mod renderer;
+pub mod render_target;
+pub mod offscreen_target;
 pub mod shaders;
+pub mod mesh;
+pub mod draw_list;
+pub mod camera;
+pub mod depth;
+pub mod texture;
+pub mod material;
+pub mod pipeline_cache;
+pub mod shader;
+pub mod debug;
+pub mod frame_manager;
+pub mod device_selection;
+pub mod graphics_config;
+pub mod capture;
+pub mod gpu_resources;
+pub mod upload_scheduler;
+pub mod compute;
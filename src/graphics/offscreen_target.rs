@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::Subbuffer,
+    command_buffer::CopyImageToBufferInfo,
+    device::Device,
+    format::Format,
+    image::{view::ImageView, ImageAccess, ImageCreateFlags, ImageDimensions, ImageUsage, SampleCount, StorageImage},
+    pipeline::graphics::viewport::Viewport,
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass},
+};
+
+use crate::errors::Errors;
+
+use super::{debug, depth, gpu_resources::GpuResources, render_target::RenderTarget};
+
+/// Color target used when rendering without a window. Pixels are stored as
+/// tightly packed RGBA8 so they can be read back and compared byte for byte.
+pub struct OffscreenTarget {
+    pub image: Arc<StorageImage>,
+    pub render_pass: Arc<RenderPass>,
+    pub framebuffers: Vec<Arc<Framebuffer>>,
+    pub viewport: Viewport,
+    readback_buffer: Subbuffer<[u8]>,
+    gpu_resources: Arc<GpuResources>,
+}
+
+impl OffscreenTarget {
+    pub const FORMAT: Format = Format::R8G8B8A8_UNORM;
+
+    pub fn new(gpu_resources: &Arc<GpuResources>, width: u32, height: u32) -> Result<OffscreenTarget, Errors> {
+        let device_manager = gpu_resources.device_manager();
+        let image = StorageImage::with_usage(
+            gpu_resources.memory_allocator().as_ref(),
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            Self::FORMAT,
+            ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ImageCreateFlags::empty(),
+            [device_manager.queue.queue_family_index()],
+        )?;
+
+        let readback_buffer = gpu_resources.readback_buffer(width as u64 * height as u64 * 4, "offscreen readback buffer")?;
+
+        debug::set_object_name(image.inner().image.as_ref(), "offscreen color attachment");
+        gpu_resources.track_image(image.inner().image);
+
+        let depth_format = depth::find_depth_format(&device_manager.physical_device)?;
+        let render_pass = Self::get_render_pass(device_manager.device.clone(), depth_format)?;
+        let view = ImageView::new_default(image.clone())?;
+        let depth_view = depth::create_depth_image(gpu_resources, depth_format, [width, height], SampleCount::Sample1)?;
+        let framebuffer = Framebuffer::new(
+            render_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![view, depth_view],
+                ..Default::default()
+            },
+        )?;
+
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [width as f32, height as f32],
+            depth_range: 0.0..1.0,
+        };
+
+        Ok(OffscreenTarget {
+            image,
+            render_pass,
+            framebuffers: vec![framebuffer],
+            viewport,
+            readback_buffer,
+            gpu_resources: gpu_resources.clone(),
+        })
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.image.dimensions().width_height()
+    }
+
+    /// Copies the current contents of the target to host memory as RGBA8 rows.
+    pub fn read_back(&self) -> Result<Vec<u8>, Errors> {
+        self.gpu_resources.execute_and_wait(|builder| {
+            builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+                self.image.clone(),
+                self.readback_buffer.clone(),
+            ))?;
+            Ok(())
+        })?;
+
+        let pixels = self.readback_buffer.read()?.to_vec();
+
+        Ok(pixels)
+    }
+
+    fn get_render_pass(device: Arc<Device>, depth_format: Format) -> Result<Arc<RenderPass>, Errors> {
+        vulkano::single_pass_renderpass!(
+            device,
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Self::FORMAT,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: depth_format,
+                    samples: 1,
+                },
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {depth},
+            },
+        )
+        .map_err(Errors::from)
+    }
+}
+
+impl RenderTarget for OffscreenTarget {
+    fn render_pass(&self) -> Arc<RenderPass> {
+        self.render_pass.clone()
+    }
+
+    fn framebuffers(&self) -> &[Arc<Framebuffer>] {
+        &self.framebuffers
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use log::{info, warn};
+use vulkano::{
+    device::{Device, Properties},
+    image::SampleCount,
+    pipeline::{
+        cache::PipelineCache as VulkanPipelineCache,
+        graphics::{
+            input_assembly::InputAssemblyState, multisample::MultisampleState, vertex_input::Vertex,
+            viewport::ViewportState,
+        },
+        ComputePipeline, GraphicsPipeline, PipelineLayout,
+    },
+    render_pass::{RenderPass, Subpass},
+    shader::ShaderModule,
+};
+
+use crate::errors::Errors;
+
+use super::{
+    debug,
+    material::{Material, PipelineState},
+    mesh::MeshVertex,
+    shader::Shader,
+};
+
+/// Identifies a pipeline: the shader pair, its fixed-function state and the
+/// render pass it draws in. Shaders and render passes compare by identity.
+#[derive(Clone)]
+pub struct PipelineKey {
+    pub vertex_shader: Arc<ShaderModule>,
+    pub fragment_shader: Arc<ShaderModule>,
+    pub state: PipelineState,
+    pub render_pass: Arc<RenderPass>,
+}
+
+impl PartialEq for PipelineKey {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.vertex_shader, &other.vertex_shader)
+            && Arc::ptr_eq(&self.fragment_shader, &other.fragment_shader)
+            && self.state == other.state
+            && Arc::ptr_eq(&self.render_pass, &other.render_pass)
+    }
+}
+
+impl Eq for PipelineKey {}
+
+impl Hash for PipelineKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.vertex_shader).hash(state);
+        Arc::as_ptr(&self.fragment_shader).hash(state);
+        self.state.hash(state);
+        Arc::as_ptr(&self.render_pass).hash(state);
+    }
+}
+
+/// Identifies a compute pipeline by its shader module.
+#[derive(Clone)]
+struct ComputePipelineKey(Arc<ShaderModule>);
+
+impl PartialEq for ComputePipelineKey {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ComputePipelineKey {}
+
+impl Hash for ComputePipelineKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state);
+    }
+}
+
+/// Size of the version one header every Vulkan pipeline cache starts with.
+const CACHE_HEADER_SIZE: usize = 32;
+const CACHE_HEADER_VERSION_ONE: u32 = 1;
+
+/// The identifying part of a Vulkan pipeline cache header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheHeader {
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub pipeline_cache_uuid: [u8; 16],
+}
+
+impl CacheHeader {
+    /// The header a driver with `properties` writes and accepts.
+    pub fn for_device(properties: &Properties) -> CacheHeader {
+        CacheHeader {
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            pipeline_cache_uuid: properties.pipeline_cache_uuid,
+        }
+    }
+
+    /// Reads the header at the start of serialized cache data. Returns `None`
+    /// for truncated data or an unknown header version.
+    pub fn parse(data: &[u8]) -> Option<CacheHeader> {
+        let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        if data.len() < CACHE_HEADER_SIZE {
+            return None;
+        }
+
+        let header_size = read_u32(0) as usize;
+        if header_size < CACHE_HEADER_SIZE || header_size > data.len() || read_u32(4) != CACHE_HEADER_VERSION_ONE {
+            return None;
+        }
+
+        Some(CacheHeader {
+            vendor_id: read_u32(8),
+            device_id: read_u32(12),
+            pipeline_cache_uuid: data[16..32].try_into().unwrap(),
+        })
+    }
+}
+
+/// Where caches go when the application doesn't pick a directory: the
+/// platform's user cache directory, or the temp directory as a fallback.
+pub fn default_cache_dir() -> PathBuf {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(env::temp_dir);
+
+    base.join("lumr").join("pipeline_cache")
+}
+
+/// Whether `layout` declares push constants for all of the first `size` bytes,
+/// as pushing them requires.
+pub(crate) fn covers_push_constants(layout: &PipelineLayout, size: u32) -> bool {
+    covered_push_constant_bytes(layout.push_constant_ranges().iter().map(|range| (range.offset, range.size))) >= size
+}
+
+/// How many bytes from offset 0 the `(offset, size)` ranges cover without a gap.
+fn covered_push_constant_bytes(ranges: impl Iterator<Item = (u32, u32)>) -> u32 {
+    let mut ranges: Vec<_> = ranges.collect();
+    ranges.sort_unstable();
+
+    let mut covered = 0;
+    for (offset, size) in ranges {
+        if offset > covered {
+            break;
+        }
+        covered = covered.max(offset + size);
+    }
+
+    covered
+}
+
+/// Builds graphics and compute pipelines on first use and keeps them for
+/// later frames.
+///
+/// Pipelines use a dynamic viewport and scissor, so resizing the target doesn't
+/// invalidate them as long as its render pass stays the same. Compiled
+/// pipeline data is also kept in a Vulkan pipeline cache that can be saved to
+/// disk, so later launches skip most of the driver's compilation work.
+pub struct PipelineCache {
+    device: Arc<Device>,
+    pipelines: HashMap<PipelineKey, Arc<GraphicsPipeline>>,
+    compute_pipelines: HashMap<ComputePipelineKey, Arc<ComputePipeline>>,
+    vulkan_cache: Arc<VulkanPipelineCache>,
+    /// File the Vulkan cache is loaded from and saved to.
+    cache_file: Option<PathBuf>,
+}
+
+impl PipelineCache {
+    /// A cache that only lives in memory.
+    pub fn new(device: Arc<Device>) -> Result<PipelineCache, Errors> {
+        let vulkan_cache = VulkanPipelineCache::empty(device.clone())?;
+
+        Ok(PipelineCache {
+            device,
+            pipelines: HashMap::new(),
+            compute_pipelines: HashMap::new(),
+            vulkan_cache,
+            cache_file: None,
+        })
+    }
+
+    /// A cache backed by a file in `directory`, named after the device UUID
+    /// and driver version so different GPUs and drivers don't share data.
+    /// Missing, unreadable or stale files start an empty cache.
+    pub fn with_directory(device: Arc<Device>, directory: impl AsRef<Path>) -> Result<PipelineCache, Errors> {
+        let properties = device.physical_device().properties();
+        let device_uuid = properties.device_uuid.unwrap_or(properties.pipeline_cache_uuid);
+        let file_name = format!(
+            "{}-{:x}.bin",
+            device_uuid.iter().map(|byte| format!("{byte:02x}")).collect::<String>(),
+            properties.driver_version,
+        );
+        let cache_file = directory.as_ref().join(file_name);
+
+        let vulkan_cache = match fs::read(&cache_file) {
+            Ok(data) if CacheHeader::parse(&data) == Some(CacheHeader::for_device(properties)) => {
+                info!("loaded pipeline cache from {}", cache_file.display());
+                // The header matches this device and driver, which is what the
+                // driver needs to safely interpret the rest of the data.
+                unsafe { VulkanPipelineCache::with_data(device.clone(), &data)? }
+            }
+            Ok(_) => {
+                warn!("discarding stale pipeline cache {}", cache_file.display());
+                VulkanPipelineCache::empty(device.clone())?
+            }
+            Err(_) => VulkanPipelineCache::empty(device.clone())?,
+        };
+
+        Ok(PipelineCache {
+            device,
+            pipelines: HashMap::new(),
+            compute_pipelines: HashMap::new(),
+            vulkan_cache,
+            cache_file: Some(cache_file),
+        })
+    }
+
+    pub fn cache_file(&self) -> Option<&Path> {
+        self.cache_file.as_deref()
+    }
+
+    /// Writes the Vulkan cache data to the cache file, if there is one.
+    pub fn save(&self) -> Result<(), Errors> {
+        let Some(cache_file) = &self.cache_file else {
+            return Ok(());
+        };
+
+        let data = self.vulkan_cache.get_data()?;
+        if let Some(directory) = cache_file.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        // Written next to the target and renamed, so a crash mid-write can't
+        // leave a truncated cache behind.
+        let temporary_file = cache_file.with_extension("tmp");
+        fs::write(&temporary_file, data)?;
+        fs::rename(&temporary_file, cache_file)?;
+
+        Ok(())
+    }
+
+    /// The pipeline drawing `material` in `render_pass`.
+    pub fn get(&mut self, material: &Material, render_pass: &Arc<RenderPass>) -> Result<Arc<GraphicsPipeline>, Errors> {
+        let key = PipelineKey {
+            vertex_shader: material.vertex_shader().module(),
+            fragment_shader: material.fragment_shader().module(),
+            state: material.state(),
+            render_pass: render_pass.clone(),
+        };
+
+        if let Some(pipeline) = self.pipelines.get(&key) {
+            return Ok(pipeline.clone());
+        }
+
+        let pipeline = Self::create_pipeline(self.device.clone(), self.vulkan_cache.clone(), &key)?;
+        self.pipelines.insert(key, pipeline.clone());
+
+        Ok(pipeline)
+    }
+
+    /// The pipeline running the current version of the compute `shader`.
+    pub fn get_compute(&mut self, shader: &Shader) -> Result<Arc<ComputePipeline>, Errors> {
+        let key = ComputePipelineKey(shader.module());
+
+        if let Some(pipeline) = self.compute_pipelines.get(&key) {
+            return Ok(pipeline.clone());
+        }
+
+        let entry_point = key
+            .0
+            .entry_point("main")
+            .ok_or_else(|| Errors::ShaderLoad("shader has no `main` entry point".to_string()))?;
+        let pipeline =
+            ComputePipeline::new(self.device.clone(), entry_point, &(), Some(self.vulkan_cache.clone()), |_| {})?;
+        debug::set_object_name(pipeline.as_ref(), "compute pipeline");
+        self.compute_pipelines.insert(key, pipeline.clone());
+
+        Ok(pipeline)
+    }
+
+    /// Number of graphics pipelines.
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+
+    pub fn compute_len(&self) -> usize {
+        self.compute_pipelines.len()
+    }
+
+    /// Drops the pipelines built from `module`, e.g. after it was reloaded.
+    pub fn remove_shader_module(&mut self, module: &Arc<ShaderModule>) {
+        self.pipelines.retain(|key, _| {
+            !Arc::ptr_eq(&key.vertex_shader, module) && !Arc::ptr_eq(&key.fragment_shader, module)
+        });
+        self.compute_pipelines.retain(|key, _| !Arc::ptr_eq(&key.0, module));
+    }
+
+    /// Drops the pipelines for other render passes, e.g. after a swapchain
+    /// was recreated with a new format.
+    pub fn retain_render_pass(&mut self, render_pass: &Arc<RenderPass>) {
+        self.pipelines.retain(|key, _| Arc::ptr_eq(&key.render_pass, render_pass));
+    }
+
+    /// Drops every pipeline, e.g. after render passes were replaced.
+    pub fn clear(&mut self) {
+        self.pipelines.clear();
+        self.compute_pipelines.clear();
+    }
+
+    fn create_pipeline(
+        device: Arc<Device>,
+        vulkan_cache: Arc<VulkanPipelineCache>,
+        key: &PipelineKey,
+    ) -> Result<Arc<GraphicsPipeline>, Errors> {
+        let missing_entry_point = || Errors::ShaderLoad("shader has no `main` entry point".to_string());
+        let vertex_entry_point = key.vertex_shader.entry_point("main").ok_or_else(missing_entry_point)?;
+        let fragment_entry_point = key.fragment_shader.entry_point("main").ok_or_else(missing_entry_point)?;
+
+        let subpass = Subpass::from(key.render_pass.clone(), 0)
+            .ok_or_else(|| Errors::Graphics("render pass has no subpass to draw in".to_string()))?;
+        let multisample_state = MultisampleState {
+            rasterization_samples: subpass.num_samples().unwrap_or(SampleCount::Sample1),
+            ..Default::default()
+        };
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(MeshVertex::per_vertex())
+            .vertex_shader(vertex_entry_point, ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_dynamic(1))
+            .fragment_shader(fragment_entry_point, ())
+            .rasterization_state(key.state.rasterization_state())
+            .depth_stencil_state(key.state.depth.depth_stencil_state())
+            .color_blend_state(key.state.color_blend_state())
+            .multisample_state(multisample_state)
+            .render_pass(subpass)
+            .build_with_cache(vulkan_cache)
+            .build(device)?;
+        debug::set_object_name(pipeline.as_ref(), "material pipeline");
+
+        Ok(pipeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_bytes(header_size: u32, version: u32, vendor_id: u32, device_id: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        for value in [header_size, version, vendor_id, device_id] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&[7; 16]);
+        data.extend_from_slice(&[0; 8]);
+        data
+    }
+
+    #[test]
+    fn header_is_parsed() {
+        let header = CacheHeader::parse(&header_bytes(32, 1, 0x10de, 0x2204)).unwrap();
+
+        assert_eq!(header, CacheHeader { vendor_id: 0x10de, device_id: 0x2204, pipeline_cache_uuid: [7; 16] });
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        assert_eq!(CacheHeader::parse(&[]), None);
+        assert_eq!(CacheHeader::parse(&header_bytes(32, 1, 1, 1)[..31]), None);
+        assert_eq!(CacheHeader::parse(&header_bytes(32, 2, 1, 1)), None);
+        assert_eq!(CacheHeader::parse(&header_bytes(16, 1, 1, 1)), None);
+        assert_eq!(CacheHeader::parse(&header_bytes(4096, 1, 1, 1)), None);
+    }
+
+    #[test]
+    fn push_constant_coverage_stops_at_gaps() {
+        assert_eq!(covered_push_constant_bytes([].into_iter()), 0);
+        assert_eq!(covered_push_constant_bytes([(64, 16), (0, 64)].into_iter()), 80);
+        assert_eq!(covered_push_constant_bytes([(0, 32), (16, 8)].into_iter()), 32);
+        assert_eq!(covered_push_constant_bytes([(0, 16), (32, 16)].into_iter()), 16);
+        assert_eq!(covered_push_constant_bytes([(16, 64)].into_iter()), 0);
+    }
+}
@@ -0,0 +1,10 @@
+use std::sync::Arc;
+
+use vulkano::render_pass::{Framebuffer, RenderPass};
+
+/// Something the `Renderer` can record draw commands into: a render pass and
+/// one framebuffer per image it can present or read back.
+pub trait RenderTarget {
+    fn render_pass(&self) -> Arc<RenderPass>;
+    fn framebuffers(&self) -> &[Arc<Framebuffer>];
+}
@@ -1,161 +1,420 @@
-use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer};
-use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::buffer::{BufferContents, Subbuffer};
 use vulkano::command_buffer::{
-    AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, RenderPassBeginInfo,
-    SubpassContents,
+    AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo, PrimaryAutoCommandBuffer,
+    RenderPassBeginInfo, SubpassContents,
 };
-use vulkano::device::{Device, Queue};
-use vulkano::memory::allocator::{StandardMemoryAllocator, AllocationCreateInfo, MemoryUsage};
-use vulkano::pipeline::GraphicsPipeline;
-use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
-use vulkano::pipeline::graphics::vertex_input::Vertex;
-use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
-use vulkano::render_pass::{RenderPass, Subpass, Framebuffer};
-use vulkano::shader::ShaderModule;
+use vulkano::format::ClearValue;
+use vulkano::image::ImageAspects;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout};
+use vulkano::pipeline::graphics::viewport::{Scissor, Viewport};
+use vulkano::render_pass::{LoadOp, RenderPass};
+use vulkano::sync::{self, GpuFuture};
+use std::path::Path;
 use std::sync::Arc;
 
-use super::device_manager::DeviceManager;
-use super::shaders;
-use super::swapchain_manager::SwapchainManager;
+use image::RgbaImage;
+use log::error;
+
+use crate::errors::Errors;
+
+use super::camera::{Camera, CameraUniform};
+use super::compute::{ComputePass, ComputeStage};
+use super::depth;
+use super::draw_list::{DrawList, ScreenRect};
+use super::frame_manager::FrameResources;
+use super::gpu_resources::GpuResources;
+use super::material::{Material, MaterialBuilder, StandardShaders};
+use super::mesh::{Mesh, MeshData};
+use super::pipeline_cache::{self, PipelineCache};
+use super::shader::{Shader, ShaderStage, ShaderWatcher};
+use super::texture::{Texture, TextureOptions};
+use super::upload_scheduler::UploadScheduler;
+use super::render_target::RenderTarget;
 
 pub struct Renderer {
-    pub command_buffers: Vec<Arc<PrimaryAutoCommandBuffer>>,
-    vertex_buffer: Subbuffer<[MyVertex]>,
-    command_buffer_allocator: StandardCommandBufferAllocator,
-    vs: Arc<ShaderModule>,
-    fs: Arc<ShaderModule>,
+    pipeline_cache: PipelineCache,
+    standard_shaders: Arc<StandardShaders>,
+    shader_watcher: Arc<ShaderWatcher>,
+    /// Used for draw commands without a material.
+    default_material: Arc<Material>,
+    gpu_resources: Arc<GpuResources>,
+    upload_scheduler: Arc<UploadScheduler>,
+    /// Async compute submissions no frame has waited on yet.
+    async_compute: Vec<Box<dyn GpuFuture>>,
 }
 
-#[derive(BufferContents, Vertex)]
+/// Per-draw data, laid out to match the `Object` push constant block.
+#[derive(BufferContents)]
 #[repr(C)]
-struct MyVertex {
-    #[format(R32G32_SFLOAT)]
-    position: [f32; 2],
+struct ObjectPushConstants {
+    model: [[f32; 4]; 4],
 }
 
 impl Renderer {
-    pub fn new(device_manager: &Arc<DeviceManager>, swapchain_manager: &SwapchainManager, viewport: &Viewport) -> Renderer {
-        let memory_allocator = StandardMemoryAllocator::new_default(device_manager.device.clone());
-
-        let vertex1 = MyVertex {
-            position: [-0.5, -0.5],
-        };
-        let vertex2 = MyVertex {
-            position: [0.0, 0.5],
-        };
-        let vertex3 = MyVertex {
-            position: [0.5, -0.25],
+    /// With a `pipeline_cache_dir`, compiled pipelines are loaded from and saved to it.
+    pub fn new(gpu_resources: &Arc<GpuResources>, pipeline_cache_dir: Option<&Path>) -> Result<Renderer, Errors> {
+        let device = gpu_resources.device();
+        let pipeline_cache = match pipeline_cache_dir {
+            Some(directory) => PipelineCache::with_directory(device.clone(), directory)?,
+            None => PipelineCache::new(device.clone())?,
         };
-        let vertex_buffer = Buffer::from_iter(
-            &memory_allocator,
-            BufferCreateInfo {
-                usage: BufferUsage::VERTEX_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                usage: MemoryUsage::Upload,
-                ..Default::default()
-            },
-            vec![vertex1, vertex2, vertex3].into_iter(),
+        let standard_shaders = StandardShaders::load(device)?;
+        let shader_watcher = ShaderWatcher::new(device.clone());
+        let default_material = MaterialBuilder::new(
+            gpu_resources.clone(),
+            standard_shaders.vertex.clone(),
+            standard_shaders.color.clone(),
         )
-        .unwrap();
-
-        let vs = shaders::vertex::vertex_shader::load(device_manager.device.clone()).expect("failed to create shader module");
-        let fs = shaders::fragment::fragment_shader::load(device_manager.device.clone()).expect("failed to create shader module");
-
-        let pipeline = Self::get_pipeline(
-            device_manager.device.clone(),
-            vs.clone(),
-            fs.clone(),
-            swapchain_manager.render_pass.clone(),
-            viewport.clone(),
-        );
-
-        let command_buffer_allocator =
-            StandardCommandBufferAllocator::new(device_manager.device.clone(), Default::default());
-
-        let command_buffers = Self::get_command_buffers(
-            &command_buffer_allocator,
-            &device_manager.queue,
-            &pipeline,
-            &swapchain_manager.framebuffers,
-            &vertex_buffer,
-        );
-
-        Renderer { command_buffers, vertex_buffer, command_buffer_allocator, vs, fs }
-    }
-
-    pub fn recreate_command_buffer(&mut self, device_manager: &Arc<DeviceManager>, swapchain_manager: &SwapchainManager, viewport: &Viewport) {
-        let new_pipeline = Self::get_pipeline(
-            device_manager.device.clone(),
-            self.vs.clone(),
-            self.fs.clone(),
-            swapchain_manager.render_pass.clone(),
-            viewport.clone(),
-        );
-
-        self.command_buffers = Self::get_command_buffers(
-            &self.command_buffer_allocator,
-            &device_manager.queue,
-            &new_pipeline,
-            &swapchain_manager.framebuffers,
-            &self.vertex_buffer,
-        );  
-    }
-
-    fn get_pipeline(
-        device: Arc<Device>,
-        vs: Arc<ShaderModule>,
-        fs: Arc<ShaderModule>,
-        render_pass: Arc<RenderPass>,
-        viewport: Viewport,
-    ) -> Arc<GraphicsPipeline> {
-        GraphicsPipeline::start()
-            .vertex_input_state(MyVertex::per_vertex())
-            .vertex_shader(vs.entry_point("main").unwrap(), ())
-            .input_assembly_state(InputAssemblyState::new())
-            .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
-            .fragment_shader(fs.entry_point("main").unwrap(), ())
-            .render_pass(Subpass::from(render_pass, 0).unwrap())
-            .build(device)
-            .unwrap()
-    }
-    
-    fn get_command_buffers(
-        command_buffer_allocator: &StandardCommandBufferAllocator,
-        queue: &Arc<Queue>,
-        pipeline: &Arc<GraphicsPipeline>,
-        framebuffers: &[Arc<Framebuffer>],
-        vertex_buffer: &Subbuffer<[MyVertex]>,
-    ) -> Vec<Arc<PrimaryAutoCommandBuffer>> {
-        framebuffers
-            .iter()
-            .map(|framebuffer| {
-                let mut builder = AutoCommandBufferBuilder::primary(
-                    command_buffer_allocator,
-                    queue.queue_family_index(),
-                    CommandBufferUsage::MultipleSubmit,
-                )
-                .unwrap();
-    
+        .build()?;
+
+        Ok(Renderer {
+            pipeline_cache,
+            standard_shaders,
+            shader_watcher,
+            default_material,
+            gpu_resources: gpu_resources.clone(),
+            upload_scheduler: UploadScheduler::new(gpu_resources.clone()),
+            async_compute: Vec::new(),
+        })
+    }
+
+    pub fn gpu_resources(&self) -> &Arc<GpuResources> {
+        &self.gpu_resources
+    }
+
+    /// Uploads assets without waiting; frames drawn by this renderer must wait
+    /// on `take_submitted`.
+    pub fn upload_scheduler(&self) -> &Arc<UploadScheduler> {
+        &self.upload_scheduler
+    }
+
+    /// Joins the uploads and async compute work submitted since the last call.
+    /// The frame's submission has to wait on it.
+    pub fn take_submitted(&mut self) -> Option<Box<dyn GpuFuture>> {
+        self.upload_scheduler
+            .take_submitted()
+            .into_iter()
+            .chain(self.async_compute.drain(..))
+            .reduce(|submitted, future| submitted.join(future).boxed())
+    }
+
+    pub fn standard_shaders(&self) -> &Arc<StandardShaders> {
+        &self.standard_shaders
+    }
+
+    pub fn shader_watcher(&self) -> &Arc<ShaderWatcher> {
+        &self.shader_watcher
+    }
+
+    pub fn pipeline_cache(&self) -> &PipelineCache {
+        &self.pipeline_cache
+    }
+
+    /// Writes compiled pipeline data to the cache directory, if there is one.
+    pub fn save_pipeline_cache(&self) -> Result<(), Errors> {
+        self.pipeline_cache.save()
+    }
+
+    /// Uploads mesh geometry to the GPU.
+    pub fn upload_mesh(&self, data: &MeshData) -> Result<Arc<Mesh>, Errors> {
+        Mesh::new(&self.gpu_resources, data)
+    }
+
+    /// Decodes an image file and uploads it as a texture.
+    pub fn load_texture(&self, path: impl AsRef<Path>, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        Texture::from_file(&self.gpu_resources, path, options)
+    }
+
+    pub fn upload_texture(&self, image: &RgbaImage, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        Texture::new(&self.gpu_resources, image, options)
+    }
+
+    /// Loads a GLSL or SPIR-V shader file that is reloaded when it changes.
+    pub fn load_shader(&self, path: impl AsRef<Path>, stage: ShaderStage) -> Result<Arc<Shader>, Errors> {
+        self.shader_watcher.load(path, stage)
+    }
+
+    /// Reloads changed shader files and drops the pipelines built from their
+    /// previous versions, so they are rebuilt when next drawn.
+    pub fn reload_changed_shaders(&mut self) {
+        for module in self.shader_watcher.poll() {
+            self.pipeline_cache.remove_shader_module(&module);
+        }
+    }
+
+    /// Starts a material drawn with the built-in vertex colored shaders.
+    pub fn material_builder(&self) -> MaterialBuilder {
+        MaterialBuilder::new(
+            self.gpu_resources.clone(),
+            self.standard_shaders.vertex.clone(),
+            self.standard_shaders.color.clone(),
+        )
+    }
+
+    /// Starts a material multiplying vertex colors with `texture`.
+    pub fn textured_material_builder(&self, texture: &Arc<Texture>) -> MaterialBuilder {
+        MaterialBuilder::new(
+            self.gpu_resources.clone(),
+            self.standard_shaders.vertex.clone(),
+            self.standard_shaders.textured.clone(),
+        )
+        .with_texture(texture)
+    }
+
+    /// Drops pipelines built for a render pass the target no longer uses.
+    pub fn recreate_target_resources(&mut self, render_target: &impl RenderTarget) {
+        self.pipeline_cache.retain_render_pass(&render_target.render_pass());
+    }
+
+    /// Records a one-time command buffer drawing `draw_list` into the
+    /// framebuffer at `framebuffer_index`, allocating from `frame_resources`.
+    /// With a `capture_buffer`, the finished color image is copied into it.
+    /// Async compute passes are submitted right away, so the command buffer
+    /// has to wait on `take_submitted`.
+    pub fn record_frame(
+        &mut self,
+        render_target: &impl RenderTarget,
+        frame_resources: &FrameResources,
+        framebuffer_index: usize,
+        draw_list: &DrawList,
+        capture_buffer: Option<Subbuffer<[u8]>>,
+    ) -> Result<PrimaryAutoCommandBuffer, Errors> {
+        let framebuffer = render_target.framebuffers()[framebuffer_index].clone();
+        let color_image = framebuffer.attachments()[0].image();
+        let render_pass = framebuffer.render_pass().clone();
+        let clear_values = Self::clear_values(&render_pass);
+
+        let [width, height] = framebuffer.extent();
+        let full_rect = ScreenRect::new(0, 0, width, height);
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &frame_resources.command_buffer_allocator,
+            self.gpu_resources.device_manager().queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        self.submit_async_compute(frame_resources, draw_list)?;
+        self.record_compute_passes(&mut builder, draw_list, ComputeStage::BeforeGraphics)?;
+
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values,
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassContents::Inline,
+            )?;
+
+        let default_material = self.default_material.clone();
+        // Camera sets are shared by all pipelines, whose set 0 layouts match,
+        // and only change with the camera or viewport.
+        let mut camera_set: Option<Arc<PersistentDescriptorSet>> = None;
+        let mut bound_view: Option<(Option<Camera>, ScreenRect)> = None;
+        let mut bound_rects: Option<(ScreenRect, ScreenRect)> = None;
+        let mut bound_pipeline: Option<Arc<GraphicsPipeline>> = None;
+        let mut bound_material: Option<&Arc<Material>> = None;
+        for command in draw_list.commands() {
+            let viewport = command.viewport.unwrap_or(full_rect);
+            let scissor = command.scissor.unwrap_or(full_rect).intersection(&full_rect);
+            if viewport.is_empty() {
+                continue;
+            }
+
+            let material = command.material.as_ref().unwrap_or(&default_material);
+            let pipeline = self.get_or_revert(&[material.vertex_shader(), material.fragment_shader()], |pipeline_cache| {
+                pipeline_cache.get(material, &render_pass)
+            })?;
+            let pipeline_layout = pipeline.layout().clone();
+
+            let pipeline_changed = bound_pipeline.as_ref().is_none_or(|bound_pipeline| !Arc::ptr_eq(bound_pipeline, &pipeline));
+            if pipeline_changed {
+                Self::check_material_layout(&pipeline_layout)?;
+                builder.bind_pipeline_graphics(pipeline.clone());
+                bound_pipeline = Some(pipeline);
+                bound_material = None;
+            }
+
+            if bound_rects != Some((viewport, scissor)) {
                 builder
-                    .begin_render_pass(
-                        RenderPassBeginInfo {
-                            clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into())],
-                            ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
-                        },
-                        SubpassContents::Inline,
-                    )
-                    .unwrap()
-                    .bind_pipeline_graphics(pipeline.clone())
-                    .bind_vertex_buffers(0, vertex_buffer.clone())
-                    .draw(vertex_buffer.len() as u32, 1, 0, 0)
-                    .unwrap()
-                    .end_render_pass()
-                    .unwrap();
-    
-                Arc::new(builder.build().unwrap())
+                    .set_viewport(0, [Self::to_viewport(viewport)])
+                    .set_scissor(0, [Scissor { origin: [scissor.x, scissor.y], dimensions: [scissor.width, scissor.height] }]);
+                bound_rects = Some((viewport, scissor));
+            }
+
+            let view = (command.camera, viewport);
+            let view_changed = bound_view != Some(view);
+            if view_changed {
+                let camera_uniform = match &command.camera {
+                    Some(camera) => CameraUniform::new(camera, viewport.width as f32 / viewport.height as f32),
+                    None => CameraUniform::identity(),
+                };
+                let camera_buffer = frame_resources.upload_buffer.push(camera_uniform)?;
+                camera_set = Some(PersistentDescriptorSet::new(
+                    self.gpu_resources.descriptor_set_allocator(),
+                    pipeline_layout.set_layouts()[0].clone(),
+                    [WriteDescriptorSet::buffer(0, camera_buffer)],
+                )?);
+                bound_view = Some(view);
+            }
+            if let Some(camera_set) = camera_set.clone().filter(|_| pipeline_changed || view_changed) {
+                builder.bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline_layout.clone(), 0, camera_set);
+            }
+
+            if bound_material.is_none_or(|bound_material| !Arc::ptr_eq(bound_material, material)) {
+                if let Some(layout) = pipeline_layout.set_layouts().get(1) {
+                    let material_set = material.descriptor_set(self.gpu_resources.descriptor_set_allocator(), layout)?;
+                    builder.bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline_layout.clone(), 1, material_set);
+                }
+                bound_material = Some(material);
+            }
+
+            builder.push_constants(
+                pipeline_layout,
+                0,
+                ObjectPushConstants { model: command.transform.to_cols_array_2d() },
+            );
+            command.mesh.draw(&mut builder)?;
+        }
+        builder.end_render_pass()?;
+
+        self.record_compute_passes(&mut builder, draw_list, ComputeStage::AfterGraphics)?;
+
+        if let Some(capture_buffer) = capture_buffer {
+            builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(color_image, capture_buffer))?;
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Submits the `Async` compute passes on the async compute queue.
+    fn submit_async_compute(&mut self, frame_resources: &FrameResources, draw_list: &DrawList) -> Result<(), Errors> {
+        if draw_list.compute_passes().iter().all(|pass| pass.stage() != ComputeStage::Async) {
+            return Ok(());
+        }
+
+        let queue = self.gpu_resources.device_manager().async_compute_queue().clone();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &frame_resources.command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        self.record_compute_passes(&mut builder, draw_list, ComputeStage::Async)?;
+
+        let future = sync::now(self.gpu_resources.device().clone())
+            .then_execute(queue, builder.build()?)?
+            .then_signal_semaphore_and_flush()?;
+        self.async_compute.push(future.boxed());
+
+        Ok(())
+    }
+
+    fn record_compute_passes(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        draw_list: &DrawList,
+        stage: ComputeStage,
+    ) -> Result<(), Errors> {
+        for pass in draw_list.compute_passes().iter().filter(|pass| pass.stage() == stage) {
+            self.record_compute_pass(builder, pass)?;
+        }
+
+        Ok(())
+    }
+
+    fn record_compute_pass(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        pass: &ComputePass,
+    ) -> Result<(), Errors> {
+        let pipeline =
+            self.get_or_revert(&[pass.shader()], |pipeline_cache| pipeline_cache.get_compute(pass.shader()))?;
+        let pipeline_layout = pipeline.layout().clone();
+        builder.bind_pipeline_compute(pipeline);
+
+        if pass.has_bindings() {
+            let set_layout = pipeline_layout
+                .set_layouts()
+                .first()
+                .ok_or_else(|| Errors::Graphics("compute shader has no descriptor set 0".to_string()))?;
+            let set = PersistentDescriptorSet::new(
+                self.gpu_resources.descriptor_set_allocator(),
+                set_layout.clone(),
+                pass.descriptor_writes(),
+            )?;
+            builder.bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set);
+        }
+        pass.record_push_constants(builder, pipeline_layout)?;
+        builder.dispatch(pass.workgroups())?;
+
+        Ok(())
+    }
+
+    /// Gets a pipeline with `get`. If that fails and some of `shaders` were
+    /// reloaded, they go back to their previous modules and `get` is retried,
+    /// so an edit that compiles but doesn't link keeps the last working shader.
+    /// Once a pipeline was built, the reloads are accepted and no longer undone.
+    fn get_or_revert<T>(
+        &mut self,
+        shaders: &[&Arc<Shader>],
+        get: impl Fn(&mut PipelineCache) -> Result<T, Errors>,
+    ) -> Result<T, Errors> {
+        let err = match get(&mut self.pipeline_cache) {
+            Ok(pipeline) => {
+                shaders.iter().for_each(|shader| shader.accept_reload());
+                return Ok(pipeline);
+            }
+            Err(err) => err,
+        };
+        let reverted: Vec<_> = shaders.iter().filter_map(|shader| shader.revert()).collect();
+        if reverted.is_empty() {
+            return Err(err);
+        }
+
+        error!("failed to build a pipeline from a reloaded shader, keeping the previous version: {err}");
+        for module in &reverted {
+            self.pipeline_cache.remove_shader_module(module);
+        }
+        get(&mut self.pipeline_cache)
+    }
+
+    /// Fails for material shaders lacking the camera set or the object push
+    /// constants every draw binds.
+    fn check_material_layout(layout: &PipelineLayout) -> Result<(), Errors> {
+        if layout.set_layouts().is_empty() {
+            return Err(Errors::ShaderLoad("material shaders must declare the `Camera` uniform in set 0".to_string()));
+        }
+        if !pipeline_cache::covers_push_constants(layout, size_of::<ObjectPushConstants>() as u32) {
+            return Err(Errors::ShaderLoad("material shaders must declare the `Object` push constant block".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Clears color attachments to blue and depth to the far plane. Attachments
+    /// that are not cleared, like a multisample resolve target, get `None`.
+    fn clear_values(render_pass: &RenderPass) -> Vec<Option<ClearValue>> {
+        render_pass
+            .attachments()
+            .iter()
+            .map(|attachment| {
+                let format = attachment.format?;
+                if attachment.load_op != LoadOp::Clear {
+                    None
+                } else if format.aspects().intersects(ImageAspects::DEPTH) {
+                    Some(depth::depth_clear_value(format))
+                } else {
+                    Some([0.0, 0.0, 1.0, 1.0].into())
+                }
             })
             .collect()
     }
+
+    fn to_viewport(rect: ScreenRect) -> Viewport {
+        Viewport {
+            origin: [rect.x as f32, rect.y as f32],
+            dimensions: [rect.width as f32, rect.height as f32],
+            depth_range: 0.0..1.0,
+        }
+    }
 }
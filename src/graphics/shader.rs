@@ -0,0 +1,238 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock, Weak},
+    time::SystemTime,
+};
+
+use log::{error, info};
+use shaderc::{CompileOptions, Compiler, EnvVersion, ShaderKind, TargetEnv};
+use vulkano::{device::Device, shader::ShaderModule};
+
+use crate::errors::Errors;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    /// Guesses the stage from a `.vert`, `.frag` or `.comp` extension,
+    /// ignoring a trailing `.spv`.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<ShaderStage> {
+        let path = path.as_ref();
+        let path = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("spv") => Path::new(path.file_stem()?),
+            _ => path,
+        };
+
+        match path.extension()?.to_str()? {
+            "vert" => Some(ShaderStage::Vertex),
+            "frag" => Some(ShaderStage::Fragment),
+            "comp" => Some(ShaderStage::Compute),
+            _ => None,
+        }
+    }
+
+    fn shader_kind(self) -> ShaderKind {
+        match self {
+            ShaderStage::Vertex => ShaderKind::Vertex,
+            ShaderStage::Fragment => ShaderKind::Fragment,
+            ShaderStage::Compute => ShaderKind::Compute,
+        }
+    }
+}
+
+/// Compiles GLSL source to SPIR-V. `file_name` only shows up in error messages.
+pub fn compile_glsl(source: &str, stage: ShaderStage, file_name: &str) -> Result<Vec<u32>, Errors> {
+    let compiler = Compiler::new().ok_or_else(|| Errors::ShaderLoad("failed to create GLSL compiler".to_string()))?;
+    let mut options =
+        CompileOptions::new().ok_or_else(|| Errors::ShaderLoad("failed to create GLSL compiler options".to_string()))?;
+    options.set_target_env(TargetEnv::Vulkan, EnvVersion::Vulkan1_0 as u32);
+
+    let artifact = compiler
+        .compile_into_spirv(source, stage.shader_kind(), file_name, "main", Some(&options))
+        .map_err(|err| Errors::ShaderLoad(err.to_string()))?;
+
+    Ok(artifact.as_binary().to_vec())
+}
+
+/// Splits a SPIR-V binary into words. Fails on files that were cut short,
+/// e.g. while a build tool is still writing them.
+fn spirv_words(bytes: &[u8]) -> Result<Vec<u32>, Errors> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(4) {
+        return Err(Errors::ShaderLoad(format!("SPIR-V size of {} bytes is not a whole number of words", bytes.len())));
+    }
+
+    Ok(bytes.chunks_exact(4).map(|word| u32::from_le_bytes(word.try_into().unwrap())).collect())
+}
+
+/// Loads a shader module from precompiled SPIR-V (`.spv`) or GLSL source.
+pub fn load_shader_module(device: &Arc<Device>, path: &Path, stage: ShaderStage) -> Result<Arc<ShaderModule>, Errors> {
+    let is_spirv = path.extension().is_some_and(|extension| extension == "spv");
+    // Vulkano parses and reflects the SPIR-V, but can't fully validate it, so
+    // it relies on the file or compiler producing a well-formed module.
+    let module = if is_spirv {
+        let words = spirv_words(&fs::read(path)?)?;
+        unsafe { ShaderModule::from_words(device.clone(), &words)? }
+    } else {
+        let source = fs::read_to_string(path)?;
+        let words = compile_glsl(&source, stage, &path.to_string_lossy())?;
+        unsafe { ShaderModule::from_words(device.clone(), &words)? }
+    };
+
+    Ok(module)
+}
+
+struct ShaderFile {
+    path: PathBuf,
+    stage: ShaderStage,
+    /// Modification time of the version currently loaded or last attempted.
+    modified: Mutex<Option<SystemTime>>,
+}
+
+/// A shader module that can be swapped out at runtime when it was loaded
+/// from a file that changes.
+pub struct Shader {
+    module: RwLock<Arc<ShaderModule>>,
+    /// The module replaced by the last reload, until it is reverted to or the
+    /// reload is accepted.
+    previous_module: Mutex<Option<Arc<ShaderModule>>>,
+    file: Option<ShaderFile>,
+}
+
+impl Shader {
+    /// Wraps a module embedded at build time, e.g. with `vulkano_shaders::shader!`.
+    pub fn from_module(module: Arc<ShaderModule>) -> Arc<Shader> {
+        Arc::new(Shader { module: RwLock::new(module), previous_module: Mutex::new(None), file: None })
+    }
+
+    pub fn from_file(device: &Arc<Device>, path: impl AsRef<Path>, stage: ShaderStage) -> Result<Arc<Shader>, Errors> {
+        let path = path.as_ref().to_path_buf();
+        let modified = fs::metadata(&path)?.modified().ok();
+        let module = load_shader_module(device, &path, stage)?;
+
+        Ok(Arc::new(Shader {
+            module: RwLock::new(module),
+            previous_module: Mutex::new(None),
+            file: Some(ShaderFile { path, stage, modified: Mutex::new(modified) }),
+        }))
+    }
+
+    /// The current version of the module.
+    pub fn module(&self) -> Arc<ShaderModule> {
+        self.module.read().unwrap().clone()
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|file| file.path.as_path())
+    }
+
+    /// Reloads the file if it was modified since the last attempt and returns
+    /// the module it replaced. On failure the current module stays in use.
+    pub fn reload_if_changed(&self, device: &Arc<Device>) -> Result<Option<Arc<ShaderModule>>, Errors> {
+        let Some(file) = &self.file else {
+            return Ok(None);
+        };
+
+        let modified = fs::metadata(&file.path)?.modified().ok();
+        {
+            let mut last_modified = file.modified.lock().unwrap();
+            if *last_modified == modified {
+                return Ok(None);
+            }
+            // Recorded before compiling so a broken file is only reported once.
+            *last_modified = modified;
+        }
+
+        let module = load_shader_module(device, &file.path, file.stage)?;
+        let old_module = std::mem::replace(&mut *self.module.write().unwrap(), module);
+        *self.previous_module.lock().unwrap() = Some(old_module.clone());
+
+        Ok(Some(old_module))
+    }
+
+    /// Keeps the current module for good, e.g. once a pipeline was built from
+    /// it, so later failures elsewhere don't revert it.
+    pub fn accept_reload(&self) {
+        self.previous_module.lock().unwrap().take();
+    }
+
+    /// Goes back to the module used before the last reload, e.g. when no
+    /// pipeline can be built from the new one. Returns the dropped module, or
+    /// `None` if there is nothing to revert to.
+    pub fn revert(&self) -> Option<Arc<ShaderModule>> {
+        let previous_module = self.previous_module.lock().unwrap().take()?;
+
+        Some(std::mem::replace(&mut *self.module.write().unwrap(), previous_module))
+    }
+}
+
+/// Keeps track of shaders loaded from files and reloads them when they change.
+pub struct ShaderWatcher {
+    device: Arc<Device>,
+    shaders: Mutex<Vec<Weak<Shader>>>,
+}
+
+impl ShaderWatcher {
+    pub fn new(device: Arc<Device>) -> Arc<ShaderWatcher> {
+        Arc::new(ShaderWatcher { device, shaders: Mutex::new(Vec::new()) })
+    }
+
+    /// Loads a shader file and watches it for changes.
+    pub fn load(&self, path: impl AsRef<Path>, stage: ShaderStage) -> Result<Arc<Shader>, Errors> {
+        let shader = Shader::from_file(&self.device, path, stage)?;
+        self.watch(&shader);
+
+        Ok(shader)
+    }
+
+    pub fn watch(&self, shader: &Arc<Shader>) {
+        self.shaders.lock().unwrap().push(Arc::downgrade(shader));
+    }
+
+    /// Checks every watched file's modification time and reloads the changed
+    /// ones. Returns the replaced modules; failures are logged.
+    pub fn poll(&self) -> Vec<Arc<ShaderModule>> {
+        let mut shaders = self.shaders.lock().unwrap();
+        shaders.retain(|shader| shader.strong_count() > 0);
+
+        let mut replaced = Vec::new();
+        for shader in shaders.iter().filter_map(Weak::upgrade) {
+            let path = shader.path().unwrap_or(Path::new("")).display().to_string();
+            match shader.reload_if_changed(&self.device) {
+                Ok(Some(old_module)) => {
+                    info!("reloaded shader {path}");
+                    replaced.push(old_module);
+                }
+                Ok(None) => {}
+                Err(err) => error!("failed to reload shader {path}, keeping the previous version: {err}"),
+            }
+        }
+
+        replaced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stage_is_guessed_from_extension() {
+        assert_eq!(ShaderStage::from_path("shaders/sprite.vert"), Some(ShaderStage::Vertex));
+        assert_eq!(ShaderStage::from_path("shaders/sprite.frag.spv"), Some(ShaderStage::Fragment));
+        assert_eq!(ShaderStage::from_path("blur.comp"), Some(ShaderStage::Compute));
+        assert_eq!(ShaderStage::from_path("sprite.glsl"), None);
+        assert_eq!(ShaderStage::from_path("sprite.spv"), None);
+    }
+
+    #[test]
+    fn truncated_spirv_is_rejected() {
+        assert_eq!(spirv_words(&[0x03, 0x02, 0x23, 0x07, 1, 0, 0, 0]).unwrap(), vec![0x0723_0203, 1]);
+        assert!(matches!(spirv_words(&[0x03, 0x02, 0x23, 0x07, 1]), Err(Errors::ShaderLoad(_))));
+        assert!(matches!(spirv_words(&[]), Err(Errors::ShaderLoad(_))));
+    }
+}
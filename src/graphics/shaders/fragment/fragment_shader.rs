@@ -2,9 +2,16 @@ vulkano_shaders::shader! {
     ty: "fragment",
     src: r"
         #version 460
+        layout(location = 0) in vec4 v_color;
+
         layout(location = 0) out vec4 f_color;
+
+        layout(set = 1, binding = 0) uniform Material {
+            vec4 base_color;
+        } material;
+
         void main() {
-            f_color = vec4(1.0, 0.0, 0.0, 1.0);
+            f_color = v_color * material.base_color;
         }
     ",
 }
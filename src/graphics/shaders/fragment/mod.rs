@@ -1 +1,2 @@
-pub mod fragment_shader;
\ No newline at end of file
+pub mod fragment_shader;
+pub mod textured_fragment_shader;
@@ -0,0 +1,20 @@
+vulkano_shaders::shader! {
+    ty: "fragment",
+    src: r"
+        #version 460
+        layout(location = 0) in vec4 v_color;
+        layout(location = 1) in vec2 v_uv;
+
+        layout(location = 0) out vec4 f_color;
+
+        layout(set = 1, binding = 0) uniform Material {
+            vec4 base_color;
+        } material;
+        layout(set = 1, binding = 1) uniform texture2D tex;
+        layout(set = 1, binding = 2) uniform sampler tex_sampler;
+
+        void main() {
+            f_color = v_color * material.base_color * texture(sampler2D(tex, tex_sampler), v_uv);
+        }
+    ",
+}
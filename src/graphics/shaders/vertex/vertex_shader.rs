@@ -2,9 +2,28 @@ vulkano_shaders::shader! {
     ty: "vertex",
     src: r"
         #version 460
-        layout(location = 0) in vec2 position;
+        layout(location = 0) in vec3 position;
+        layout(location = 2) in vec2 uv;
+        layout(location = 3) in vec4 color;
+
+        layout(location = 0) out vec4 v_color;
+        layout(location = 1) out vec2 v_uv;
+
+        layout(set = 0, binding = 0) uniform Camera {
+            mat4 view;
+            mat4 projection;
+            mat4 view_projection;
+            vec4 position;
+        } camera;
+
+        layout(push_constant) uniform Object {
+            mat4 model;
+        } object;
+
         void main() {
-            gl_Position = vec4(position, 0.0, 1.0);
+            gl_Position = camera.view_projection * object.model * vec4(position, 1.0);
+            v_color = color;
+            v_uv = uv;
         }
     ",
 }
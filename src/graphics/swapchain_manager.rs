@@ -1,108 +1,521 @@
 use std::sync::Arc;
 
 use vulkano::{
-    swapchain::{Swapchain, SwapchainCreateInfo, SwapchainCreationError}, 
-    image::{SwapchainImage, ImageUsage, view::ImageView},
-    device::Device, 
+    swapchain::{ColorSpace, PresentMode, Surface, Swapchain, SwapchainCreateInfo, SwapchainCreationError},
+    image::{SwapchainImage, AttachmentImage, ImageAccess, ImageUsage, SampleCount, SampleCounts, ImageViewAbstract, view::ImageView},
+    device::{physical::PhysicalDevice, Device, DeviceOwned},
+    format::{Format, NumericType},
     render_pass::{RenderPass, Framebuffer, FramebufferCreateInfo},
 };
+use log::info;
 use winit::dpi::PhysicalSize;
-use super::{window::GraphicsWindow, device_manager::DeviceManager};
+use super::{window::GraphicsWindow, gpu_resources::GpuResources, render_target::RenderTarget, depth, debug, capture};
+use crate::errors::Errors;
+
+/// Formats for an sRGB swapchain, best first. The `_SRGB` formats encode
+/// shader output on write; the `_UNORM` ones leave gamma to the shaders.
+const SRGB_FORMATS: [Format; 5] = [
+    Format::B8G8R8A8_SRGB,
+    Format::R8G8B8A8_SRGB,
+    Format::A8B8G8R8_SRGB_PACK32,
+    Format::B8G8R8A8_UNORM,
+    Format::R8G8B8A8_UNORM,
+];
+const HDR10_FORMATS: [Format; 2] = [Format::A2B10G10R10_UNORM_PACK32, Format::A2R10G10B10_UNORM_PACK32];
+const EXTENDED_SRGB_FORMATS: [Format; 1] = [Format::R16G16B16A16_SFLOAT];
+
+/// The output color space to ask for. HDR spaces need
+/// `VK_EXT_swapchain_colorspace` and a display that offers them, and fall
+/// back to `Srgb` otherwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorSpacePreference {
+    #[default]
+    Srgb,
+    /// 10-bit PQ encoded output, `HDR10_ST2084`.
+    Hdr10,
+    /// Linear half-float output that may exceed 1.0, `EXTENDED_SRGB_LINEAR`.
+    ExtendedSrgb,
+}
+
+/// A swapchain image format with the color space it is presented in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfaceFormat {
+    pub format: Format,
+    pub color_space: ColorSpace,
+}
+
+impl SurfaceFormat {
+    /// Picks the best supported format for `preference`, or the first one
+    /// the surface offers when none of the known formats are supported.
+    pub fn choose(supported: &[(Format, ColorSpace)], preference: ColorSpacePreference) -> Option<SurfaceFormat> {
+        let find = |formats: &[Format], color_space: ColorSpace| {
+            formats
+                .iter()
+                .find(|format| supported.contains(&(**format, color_space)))
+                .map(|&format| SurfaceFormat { format, color_space })
+        };
+
+        let hdr = match preference {
+            ColorSpacePreference::Srgb => None,
+            ColorSpacePreference::Hdr10 => find(&HDR10_FORMATS, ColorSpace::Hdr10St2084),
+            ColorSpacePreference::ExtendedSrgb => find(&EXTENDED_SRGB_FORMATS, ColorSpace::ExtendedSrgbLinear),
+        };
+
+        hdr.or_else(|| find(&SRGB_FORMATS, ColorSpace::SrgbNonLinear))
+            .or_else(|| supported.first().map(|&(format, color_space)| SurfaceFormat { format, color_space }))
+    }
+
+    /// Whether the hardware converts linear shader output to sRGB on write.
+    /// When it doesn't and the color space is `SrgbNonLinear`, shaders have
+    /// to encode gamma themselves.
+    pub fn encodes_srgb(&self) -> bool {
+        self.format.type_color() == Some(NumericType::SRGB)
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.color_space, ColorSpace::Hdr10St2084 | ColorSpace::ExtendedSrgbLinear)
+    }
+}
+
+/// How images are presented to the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapchainConfig {
+    /// Preferred present mode. Falls back to the closest mode the surface
+    /// supports, ending at `Fifo`, which every surface has.
+    pub present_mode: PresentMode,
+    /// Desired number of swapchain images, clamped to the surface limits.
+    /// `None` uses the surface minimum.
+    pub image_count: Option<u32>,
+    pub color_space: ColorSpacePreference,
+    /// Samples per pixel for multisample anti-aliasing: 1 (off), 2, 4 or 8.
+    /// Lowered to what the device supports.
+    pub msaa_samples: u32,
+}
+
+impl Default for SwapchainConfig {
+    fn default() -> Self {
+        SwapchainConfig {
+            present_mode: PresentMode::Fifo,
+            image_count: None,
+            color_space: ColorSpacePreference::Srgb,
+            msaa_samples: 1,
+        }
+    }
+}
+
+impl SwapchainConfig {
+    /// `Fifo` with vsync, otherwise `Immediate` for the lowest latency.
+    pub fn with_vsync(mut self, vsync: bool) -> SwapchainConfig {
+        self.present_mode = if vsync { PresentMode::Fifo } else { PresentMode::Immediate };
+        self
+    }
+
+    pub fn with_msaa_samples(mut self, msaa_samples: u32) -> SwapchainConfig {
+        self.msaa_samples = msaa_samples;
+        self
+    }
+
+    /// Whether the preferred present mode waits for vertical blank.
+    pub fn vsync(&self) -> bool {
+        matches!(self.present_mode, PresentMode::Fifo | PresentMode::FifoRelaxed | PresentMode::Mailbox)
+    }
+
+    /// The supported mode closest to `present_mode`. Modes without tearing
+    /// only fall back to other modes without tearing.
+    pub fn choose_present_mode(&self, supported: &[PresentMode]) -> PresentMode {
+        let preferences: &[PresentMode] = match self.present_mode {
+            PresentMode::Immediate => &[PresentMode::Immediate, PresentMode::Mailbox],
+            PresentMode::Mailbox => &[PresentMode::Mailbox],
+            PresentMode::FifoRelaxed => &[PresentMode::FifoRelaxed],
+            _ => &[],
+        };
+
+        preferences
+            .iter()
+            .copied()
+            .find(|present_mode| supported.contains(present_mode))
+            .unwrap_or(PresentMode::Fifo)
+    }
+
+    /// `image_count` clamped to the surface's limits; `max_image_count` of
+    /// `None` means there is no upper limit.
+    pub fn choose_image_count(&self, min_image_count: u32, max_image_count: Option<u32>) -> u32 {
+        let image_count = self.image_count.unwrap_or(min_image_count).max(min_image_count);
+
+        max_image_count.map_or(image_count, |max_image_count| image_count.min(max_image_count))
+    }
+
+    /// The highest sample count in `supported` that does not exceed `msaa_samples`.
+    pub fn choose_sample_count(&self, supported: SampleCounts) -> SampleCount {
+        [SampleCount::Sample8, SampleCount::Sample4, SampleCount::Sample2]
+            .into_iter()
+            .find(|samples| u32::from(*samples) <= self.msaa_samples && supported.contains_enum(*samples))
+            .unwrap_or(SampleCount::Sample1)
+    }
+}
 
 pub struct SwapchainManager {
     pub swapchain: Arc<Swapchain>,
     pub images: Vec<Arc<SwapchainImage>>,
     pub render_pass: Arc<RenderPass>,
     pub framebuffers: Vec<Arc<Framebuffer>>,
+    pub depth_format: Format,
+    /// The color format and space of the swapchain images.
+    pub surface_format: SurfaceFormat,
+    /// Samples per pixel of the color and depth attachments drawn into.
+    pub samples: SampleCount,
+    config: SwapchainConfig,
+    gpu_resources: Arc<GpuResources>,
 }
 
 impl SwapchainManager {
-    pub fn new(graphics_window: &GraphicsWindow, device_manager: Arc<DeviceManager>) -> SwapchainManager {
+    pub fn new(
+        graphics_window: &GraphicsWindow,
+        gpu_resources: Arc<GpuResources>,
+        config: SwapchainConfig,
+    ) -> Result<SwapchainManager, Errors> {
+        let device_manager = gpu_resources.device_manager();
         let (swapchain, images) = {
             let caps = device_manager.physical_device
-                .surface_capabilities(graphics_window.surface.as_ref(), Default::default())
-                .expect("failed to get surface capabilities");
+                .surface_capabilities(graphics_window.surface.as_ref(), Default::default())?;
+            let (present_mode, min_image_count) =
+                Self::choose_present_settings(&device_manager.physical_device, &graphics_window.surface, &config)?;
 
             let dimensions = graphics_window.window.inner_size();
-            let composite_alpha = caps.supported_composite_alpha.into_iter().next().unwrap();
-            let image_format = Some(
-                device_manager.physical_device
-                    .surface_formats(&graphics_window.surface, Default::default())
-                    .unwrap()[0]
-                    .0,
-            );
+            let composite_alpha = caps.supported_composite_alpha
+                .into_iter()
+                .next()
+                .ok_or_else(|| Errors::Surface("no supported composite alpha mode".to_string()))?;
+            let surface_format = Self::choose_surface_format(&device_manager.physical_device, &graphics_window.surface, &config)?;
+            // Copying from the images allows screenshots, where supported.
+            let image_usage = ImageUsage::COLOR_ATTACHMENT
+                | (caps.supported_usage_flags & ImageUsage::TRANSFER_SRC);
 
             Swapchain::new(
                 device_manager.device.clone(),
                 graphics_window.surface.clone(),
                 SwapchainCreateInfo {
-                    min_image_count: caps.min_image_count,
-                    image_format,
+                    min_image_count,
+                    image_format: Some(surface_format.format),
+                    image_color_space: surface_format.color_space,
                     image_extent: dimensions.into(),
-                    image_usage: ImageUsage::COLOR_ATTACHMENT,
+                    image_usage,
                     composite_alpha,
+                    present_mode,
                     ..Default::default()
                 },
-            )
-            .unwrap()
+            )?
         };
 
-        let render_pass = Self::get_render_pass(device_manager.device.clone(), swapchain.clone());
-        let framebuffers = Self::get_framebuffers(&images, render_pass.clone());
+        let depth_format = depth::find_depth_format(&device_manager.physical_device)?;
+
+        let samples = Self::choose_sample_count(&device_manager.physical_device, &config);
+
+        let render_pass = Self::get_render_pass(device_manager.device.clone(), swapchain.image_format(), depth_format, samples)?;
+        let framebuffers = Self::get_framebuffers(&images, render_pass.clone(), &gpu_resources, depth_format, samples)?;
+
+        let surface_format = SurfaceFormat { format: swapchain.image_format(), color_space: swapchain.image_color_space() };
+        info!("presenting {:?} in {:?}", surface_format.format, surface_format.color_space);
 
-        SwapchainManager { swapchain, images, render_pass, framebuffers }
+        Ok(SwapchainManager {
+            swapchain,
+            images,
+            render_pass,
+            framebuffers,
+            depth_format,
+            surface_format,
+            samples,
+            config,
+            gpu_resources,
+        })
     }
 
-    pub fn recreate(&mut self, updated_dimensions: PhysicalSize<u32>) {
+    /// The image format, if the swapchain images can be copied into
+    /// screenshots.
+    pub fn capture_format(&self) -> Option<Format> {
+        let format = self.swapchain.image_format();
+
+        (self.swapchain.image_usage().intersects(ImageUsage::TRANSFER_SRC) && capture::can_capture(format))
+            .then_some(format)
+    }
+
+    pub fn config(&self) -> SwapchainConfig {
+        self.config
+    }
+
+    /// Takes effect on the next `recreate`.
+    pub fn set_config(&mut self, config: SwapchainConfig) {
+        self.config = config;
+    }
+
+    pub fn recreate(&mut self, updated_dimensions: PhysicalSize<u32>) -> Result<(), Errors> {
+        let (present_mode, min_image_count) = Self::choose_present_settings(
+            self.swapchain.device().physical_device(),
+            self.swapchain.surface(),
+            &self.config,
+        )?;
+        let surface_format =
+            Self::choose_surface_format(self.swapchain.device().physical_device(), self.swapchain.surface(), &self.config)?;
+
         let (new_swapchain, new_images) = match self.swapchain.recreate(
             SwapchainCreateInfo {
             image_extent: updated_dimensions.into(),
+            present_mode,
+            min_image_count,
+            image_format: Some(surface_format.format),
+            image_color_space: surface_format.color_space,
             ..self.swapchain.create_info()
         }) {
             Ok(r) => r,
-            Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return,
-            Err(e) => panic!("failed to recreate swapchain: {e}"),
+            Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return Ok(()),
+            Err(e) => return Err(e.into()),
         };
         self.swapchain = new_swapchain;
-        self.framebuffers = Self::get_framebuffers(&new_images, self.render_pass.clone());
+        let samples = Self::choose_sample_count(self.swapchain.device().physical_device(), &self.config);
+        if surface_format != self.surface_format || samples != self.samples {
+            // Framebuffers for the new format or sample count need a matching render pass.
+            self.render_pass = Self::get_render_pass(
+                self.swapchain.device().clone(),
+                self.swapchain.image_format(),
+                self.depth_format,
+                samples,
+            )?;
+            if surface_format != self.surface_format {
+                info!("presenting {:?} in {:?}", surface_format.format, surface_format.color_space);
+            }
+            self.surface_format = surface_format;
+            self.samples = samples;
+        }
+        self.framebuffers = Self::get_framebuffers(
+            &new_images,
+            self.render_pass.clone(),
+            &self.gpu_resources,
+            self.depth_format,
+            self.samples,
+        )?;
+        self.images = new_images;
+
+        Ok(())
+    }
+
+    fn choose_present_settings(
+        physical_device: &PhysicalDevice,
+        surface: &Surface,
+        config: &SwapchainConfig,
+    ) -> Result<(PresentMode, u32), Errors> {
+        let caps = physical_device.surface_capabilities(surface, Default::default())?;
+        let supported_present_modes: Vec<PresentMode> = physical_device.surface_present_modes(surface)?.collect();
+        let present_mode = config.choose_present_mode(&supported_present_modes);
+        if present_mode != config.present_mode {
+            info!("present mode {:?} is not supported, using {:?}", config.present_mode, present_mode);
+        }
+
+        Ok((present_mode, config.choose_image_count(caps.min_image_count, caps.max_image_count)))
+    }
+
+    fn choose_surface_format(
+        physical_device: &PhysicalDevice,
+        surface: &Surface,
+        config: &SwapchainConfig,
+    ) -> Result<SurfaceFormat, Errors> {
+        let supported = physical_device.surface_formats(surface, Default::default())?;
+
+        SurfaceFormat::choose(&supported, config.color_space)
+            .ok_or_else(|| Errors::Surface("surface reports no formats".to_string()))
+    }
+
+    fn choose_sample_count(physical_device: &PhysicalDevice, config: &SwapchainConfig) -> SampleCount {
+        let properties = physical_device.properties();
+        let supported = properties.framebuffer_color_sample_counts & properties.framebuffer_depth_sample_counts;
+        let samples = config.choose_sample_count(supported);
+        if u32::from(samples) != config.msaa_samples.max(1) {
+            info!("{}x MSAA is not supported, using {:?}", config.msaa_samples, samples);
+        }
+
+        samples
     }
 
-    fn get_render_pass(device: Arc<Device>, swapchain: Arc<Swapchain>) -> Arc<RenderPass> {
+    /// Attachments are the swapchain image, depth and, with multisampling, the
+    /// multisampled color attachment that is resolved into the swapchain image.
+    fn get_render_pass(
+        device: Arc<Device>,
+        format: Format,
+        depth_format: Format,
+        samples: SampleCount,
+    ) -> Result<Arc<RenderPass>, Errors> {
+        if samples == SampleCount::Sample1 {
+            return vulkano::single_pass_renderpass!(
+                device,
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: Store,
+                        format: format,
+                        samples: 1,
+                    },
+                    depth: {
+                        load: Clear,
+                        store: DontCare,
+                        format: depth_format,
+                        samples: 1,
+                    },
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {depth},
+                },
+            )
+            .map_err(Errors::from);
+        }
+
         vulkano::single_pass_renderpass!(
             device,
             attachments: {
                 color: {
-                    load: Clear,
+                    load: DontCare,
                     store: Store,
-                    format: swapchain.image_format(), // set the format the same as the swapchain
+                    format: format,
                     samples: 1,
                 },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: depth_format,
+                    samples: u32::from(samples),
+                },
+                multisampled_color: {
+                    load: Clear,
+                    store: DontCare,
+                    format: format,
+                    samples: u32::from(samples),
+                },
             },
             pass: {
-                color: [color],
-                depth_stencil: {},
+                color: [multisampled_color],
+                depth_stencil: {depth},
+                resolve: [color],
             },
         )
-        .unwrap()
+        .map_err(Errors::from)
     }
 
     fn get_framebuffers(
         images: &[Arc<SwapchainImage>],
         render_pass: Arc<RenderPass>,
-    ) -> Vec<Arc<Framebuffer>> {
+        gpu_resources: &GpuResources,
+        depth_format: Format,
+        samples: SampleCount,
+    ) -> Result<Vec<Arc<Framebuffer>>, Errors> {
         images
             .iter()
             .map(|image| {
-                let view = ImageView::new_default(image.clone()).unwrap();
+                let dimensions = image.dimensions().width_height();
+                let view = ImageView::new_default(image.clone())?;
+                // One depth image per swapchain image, so frames in flight
+                // never share one.
+                let depth_view = depth::create_depth_image(gpu_resources, depth_format, dimensions, samples)?;
+                let mut attachments: Vec<Arc<dyn ImageViewAbstract>> = vec![view, depth_view];
+                if samples != SampleCount::Sample1 {
+                    let multisampled_image = AttachmentImage::transient_multisampled(
+                        gpu_resources.memory_allocator().as_ref(),
+                        dimensions,
+                        samples,
+                        image.format(),
+                    )?;
+                    debug::set_object_name(multisampled_image.inner().image.as_ref(), "multisampled color attachment");
+                    gpu_resources.track_image(multisampled_image.inner().image);
+                    attachments.push(ImageView::new_default(multisampled_image)?);
+                }
+
                 Framebuffer::new(
                     render_pass.clone(),
                     FramebufferCreateInfo {
-                        attachments: vec![view],
+                        attachments,
                         ..Default::default()
                     },
                 )
-                .unwrap()
+                .map_err(Errors::from)
             })
-            .collect::<Vec<_>>()
+            .collect()
+    }
+}
+
+impl RenderTarget for SwapchainManager {
+    fn render_pass(&self) -> Arc<RenderPass> {
+        self.render_pass.clone()
+    }
+
+    fn framebuffers(&self) -> &[Arc<Framebuffer>] {
+        &self.framebuffers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn present_mode_falls_back_without_adding_tearing() {
+        let fifo_only = [PresentMode::Fifo];
+        let with_mailbox = [PresentMode::Fifo, PresentMode::Mailbox];
+        let config = |present_mode| SwapchainConfig { present_mode, ..Default::default() };
+
+        assert_eq!(config(PresentMode::Mailbox).choose_present_mode(&with_mailbox), PresentMode::Mailbox);
+        assert_eq!(config(PresentMode::Mailbox).choose_present_mode(&fifo_only), PresentMode::Fifo);
+        assert_eq!(config(PresentMode::Immediate).choose_present_mode(&with_mailbox), PresentMode::Mailbox);
+        assert_eq!(config(PresentMode::FifoRelaxed).choose_present_mode(&fifo_only), PresentMode::Fifo);
+    }
+
+    #[test]
+    fn image_count_is_clamped_to_surface_limits() {
+        let config = |image_count| SwapchainConfig { image_count, ..Default::default() };
+
+        assert_eq!(config(None).choose_image_count(2, Some(8)), 2);
+        assert_eq!(config(Some(3)).choose_image_count(2, Some(8)), 3);
+        assert_eq!(config(Some(1)).choose_image_count(2, Some(8)), 2);
+        assert_eq!(config(Some(16)).choose_image_count(2, Some(8)), 8);
+        assert_eq!(config(Some(16)).choose_image_count(2, None), 16);
+    }
+
+    #[test]
+    fn srgb_formats_are_preferred() {
+        let supported = [
+            (Format::B8G8R8A8_UNORM, ColorSpace::SrgbNonLinear),
+            (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear),
+        ];
+
+        let surface_format = SurfaceFormat::choose(&supported, ColorSpacePreference::Srgb).unwrap();
+
+        assert_eq!(surface_format.format, Format::B8G8R8A8_SRGB);
+        assert!(surface_format.encodes_srgb());
+    }
+
+    #[test]
+    fn hdr_falls_back_to_srgb() {
+        let sdr_only = [(Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear)];
+        let with_hdr10 = [
+            (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear),
+            (Format::A2B10G10R10_UNORM_PACK32, ColorSpace::Hdr10St2084),
+        ];
+
+        let hdr10 = SurfaceFormat::choose(&with_hdr10, ColorSpacePreference::Hdr10).unwrap();
+        assert_eq!(hdr10.color_space, ColorSpace::Hdr10St2084);
+        assert!(hdr10.is_hdr());
+        let fallback = SurfaceFormat::choose(&sdr_only, ColorSpacePreference::ExtendedSrgb).unwrap();
+        assert_eq!(fallback.format, Format::B8G8R8A8_SRGB);
+        assert!(SurfaceFormat::choose(&[], ColorSpacePreference::Srgb).is_none());
+    }
+
+    #[test]
+    fn sample_count_is_clamped_to_device_support() {
+        let supported = SampleCounts::SAMPLE_1 | SampleCounts::SAMPLE_2 | SampleCounts::SAMPLE_4;
+        let config = |msaa_samples| SwapchainConfig::default().with_msaa_samples(msaa_samples);
+
+        assert_eq!(config(0).choose_sample_count(supported), SampleCount::Sample1);
+        assert_eq!(config(1).choose_sample_count(supported), SampleCount::Sample1);
+        assert_eq!(config(4).choose_sample_count(supported), SampleCount::Sample4);
+        assert_eq!(config(6).choose_sample_count(supported), SampleCount::Sample4);
+        assert_eq!(config(8).choose_sample_count(supported), SampleCount::Sample4);
+        assert_eq!(config(8).choose_sample_count(SampleCounts::SAMPLE_1), SampleCount::Sample1);
     }
 }
@@ -0,0 +1,232 @@
+use std::{path::Path, sync::Arc};
+
+use image::RgbaImage;
+use vulkano::{
+    buffer::BufferUsage,
+    command_buffer::{
+        AutoCommandBufferBuilder, BlitImageInfo, BufferImageCopy, CopyBufferToImageInfo, ImageBlit,
+        PrimaryAutoCommandBuffer,
+    },
+    device::Device,
+    format::Format,
+    image::{
+        view::ImageView, ImageAccess, ImageCreateFlags, ImageDimensions, ImageLayout, ImageSubresourceLayers,
+        ImageUsage, ImmutableImage, MipmapsCount,
+    },
+    sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode, LOD_CLAMP_NONE},
+};
+
+use crate::errors::Errors;
+
+use super::{debug, gpu_resources::GpuResources};
+
+/// How a texture is filtered and wrapped when sampled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerConfig {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+    /// Wrap modes for the U and V coordinates.
+    pub address_mode: [SamplerAddressMode; 2],
+    /// Maximum anisotropy, clamped to what the device supports. Ignored when
+    /// the device has no anisotropic filtering.
+    pub anisotropy: Option<f32>,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        SamplerConfig {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_mode: SamplerMipmapMode::Linear,
+            address_mode: [SamplerAddressMode::Repeat; 2],
+            anisotropy: None,
+        }
+    }
+}
+
+impl SamplerConfig {
+    /// Unfiltered, clamped sampling, e.g. for pixel art or lookup tables.
+    pub fn nearest() -> SamplerConfig {
+        SamplerConfig {
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            mipmap_mode: SamplerMipmapMode::Nearest,
+            address_mode: [SamplerAddressMode::ClampToEdge; 2],
+            anisotropy: None,
+        }
+    }
+
+    pub fn create_sampler(&self, device: &Arc<Device>) -> Result<Arc<Sampler>, Errors> {
+        let anisotropy = self
+            .anisotropy
+            .filter(|_| device.enabled_features().sampler_anisotropy)
+            .map(|anisotropy| anisotropy.clamp(1.0, device.physical_device().properties().max_sampler_anisotropy));
+
+        Ok(Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: self.mag_filter,
+                min_filter: self.min_filter,
+                mipmap_mode: self.mipmap_mode,
+                address_mode: [self.address_mode[0], self.address_mode[1], SamplerAddressMode::Repeat],
+                anisotropy,
+                lod: 0.0..=LOD_CLAMP_NONE,
+                ..Default::default()
+            },
+        )?)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// Treat the pixels as sRGB color, which is right for most color maps but
+    /// not for normal maps or other data.
+    pub srgb: bool,
+    /// Generate a full mip chain.
+    pub mipmaps: bool,
+    pub sampler: SamplerConfig,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        TextureOptions { srgb: true, mipmaps: true, sampler: SamplerConfig::default() }
+    }
+}
+
+/// An image on the GPU together with the sampler used to read it.
+pub struct Texture {
+    pub view: Arc<ImageView<ImmutableImage>>,
+    pub sampler: Arc<Sampler>,
+}
+
+impl Texture {
+    /// Decodes a PNG, JPEG, BMP or TGA file and uploads it.
+    pub fn from_file(
+        gpu_resources: &GpuResources,
+        path: impl AsRef<Path>,
+        options: &TextureOptions,
+    ) -> Result<Arc<Texture>, Errors> {
+        let image = image::open(path)?.into_rgba8();
+
+        Self::new(gpu_resources, &image, options)
+    }
+
+    /// Decodes an encoded image, guessing the format from its contents.
+    pub fn from_bytes(
+        gpu_resources: &GpuResources,
+        bytes: &[u8],
+        options: &TextureOptions,
+    ) -> Result<Arc<Texture>, Errors> {
+        let image = image::load_from_memory(bytes)?.into_rgba8();
+
+        Self::new(gpu_resources, &image, options)
+    }
+
+    /// Uploads `image` through a staging buffer and waits for the copy and
+    /// mipmap generation to finish.
+    pub fn new(
+        gpu_resources: &GpuResources,
+        image: &RgbaImage,
+        options: &TextureOptions,
+    ) -> Result<Arc<Texture>, Errors> {
+        gpu_resources.execute_and_wait(|builder| {
+            let texture = Self::record_upload(gpu_resources, image, options, builder)?;
+            texture.record_mipmaps(builder)?;
+
+            Ok(texture)
+        })
+    }
+
+    /// Records the copy of `image` into the first mip level of a new texture.
+    /// Any queue can execute `builder`; with `options.mipmaps`, the smaller
+    /// levels have to be filled by [`Texture::record_mipmaps`] afterwards.
+    pub fn record_upload(
+        gpu_resources: &GpuResources,
+        image: &RgbaImage,
+        options: &TextureOptions,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<Arc<Texture>, Errors> {
+        let staging_buffer = gpu_resources.host_buffer_from_iter(
+            BufferUsage::TRANSFER_SRC,
+            image.as_raw().iter().copied(),
+            "texture staging buffer",
+        )?;
+
+        // The image is shared by all queue families, so it needs no ownership
+        // transfer when copied on the transfer queue and blitted on graphics.
+        let dimensions = ImageDimensions::Dim2d { width: image.width(), height: image.height(), array_layers: 1 };
+        let format = if options.srgb { Format::R8G8B8A8_SRGB } else { Format::R8G8B8A8_UNORM };
+        let (mip_levels, usage) = if options.mipmaps {
+            (MipmapsCount::Log2, ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC | ImageUsage::SAMPLED)
+        } else {
+            (MipmapsCount::One, ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED)
+        };
+        let (gpu_image, initializer) = ImmutableImage::uninitialized(
+            gpu_resources.memory_allocator().as_ref(),
+            dimensions,
+            format,
+            mip_levels,
+            usage,
+            ImageCreateFlags::empty(),
+            ImageLayout::ShaderReadOnlyOptimal,
+            gpu_resources.device().active_queue_family_indices().iter().copied(),
+        )?;
+
+        // Copying through the initializer moves every level out of the
+        // undefined layout, so the mipmaps can be blitted in a later submission.
+        builder.copy_buffer_to_image(CopyBufferToImageInfo {
+            regions: [BufferImageCopy {
+                image_subresource: ImageSubresourceLayers::from_parameters(format, 1),
+                image_extent: dimensions.width_height_depth(),
+                ..Default::default()
+            }]
+            .into(),
+            ..CopyBufferToImageInfo::buffer_image(staging_buffer, initializer)
+        })?;
+
+        debug::set_object_name(gpu_image.inner().image.as_ref(), "texture");
+        gpu_resources.track_image(gpu_image.inner().image);
+        let view = ImageView::new_default(gpu_image)?;
+        let sampler = options.sampler.create_sampler(gpu_resources.device())?;
+
+        Ok(Arc::new(Texture { view, sampler }))
+    }
+
+    /// Records the generation of the smaller mip levels by blitting each level
+    /// into the next. `builder` must be for a graphics queue and execute after
+    /// the upload.
+    pub fn record_mipmaps(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<(), Errors> {
+        let image = self.view.image();
+        let dimensions = image.dimensions();
+
+        for level in 1..image.mip_levels() {
+            let [src_size, dst_size] = [level - 1, level].map(|level| {
+                dimensions.mip_level_dimensions(level).unwrap().width_height_depth()
+            });
+
+            builder.blit_image(BlitImageInfo {
+                regions: [ImageBlit {
+                    src_subresource: ImageSubresourceLayers { mip_level: level - 1, ..image.subresource_layers() },
+                    src_offsets: [[0; 3], src_size],
+                    dst_subresource: ImageSubresourceLayers { mip_level: level, ..image.subresource_layers() },
+                    dst_offsets: [[0; 3], dst_size],
+                    ..Default::default()
+                }]
+                .into(),
+                filter: Filter::Linear,
+                ..BlitImageInfo::images(image.clone(), image.clone())
+            })?;
+        }
+
+        Ok(())
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.view.image().dimensions().width_height()
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.view.image().mip_levels()
+    }
+}
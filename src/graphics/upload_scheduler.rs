@@ -0,0 +1,124 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use image::RgbaImage;
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer},
+    device::Queue,
+    sync::{self, GpuFuture},
+};
+
+use crate::errors::Errors;
+
+use super::{
+    gpu_resources::GpuResources,
+    mesh::{Mesh, MeshData},
+    texture::{Texture, TextureOptions},
+};
+
+type UploadFuture = Box<dyn GpuFuture + Send + Sync>;
+
+/// Streams meshes and textures to the GPU without waiting for the copies, so
+/// loading large assets doesn't stall a frame.
+///
+/// Copies run on the transfer queue where the device has one. Every upload
+/// signals a semaphore the next frame's submission waits on, so assets can be
+/// drawn as soon as they are returned. The smaller mip levels of a texture are
+/// blitted in a graphics queue submission that waits on its copy.
+pub struct UploadScheduler {
+    gpu_resources: Arc<GpuResources>,
+    /// Uploads no frame has waited on yet.
+    submitted: Mutex<Vec<UploadFuture>>,
+}
+
+impl UploadScheduler {
+    pub fn new(gpu_resources: Arc<GpuResources>) -> Arc<UploadScheduler> {
+        Arc::new(UploadScheduler { gpu_resources, submitted: Mutex::new(Vec::new()) })
+    }
+
+    pub fn upload_mesh(&self, data: &MeshData) -> Result<Arc<Mesh>, Errors> {
+        let queue = self.gpu_resources.device_manager().upload_queue();
+
+        self.submit(queue, |builder| Mesh::record_upload(&self.gpu_resources, data, builder))
+    }
+
+    /// Decodes a PNG, JPEG, BMP or TGA file and uploads it.
+    pub fn load_texture(&self, path: impl AsRef<Path>, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        let image = image::open(path)?.into_rgba8();
+
+        self.upload_texture(&image, options)
+    }
+
+    pub fn upload_texture(&self, image: &RgbaImage, options: &TextureOptions) -> Result<Arc<Texture>, Errors> {
+        let device_manager = self.gpu_resources.device_manager();
+        let upload_queue = device_manager.upload_queue();
+        let graphics_queue = &device_manager.queue;
+
+        if !options.mipmaps || Arc::ptr_eq(upload_queue, graphics_queue) {
+            return self.submit(upload_queue, |builder| {
+                let texture = Texture::record_upload(&self.gpu_resources, image, options, builder)?;
+                if options.mipmaps {
+                    texture.record_mipmaps(builder)?;
+                }
+
+                Ok(texture)
+            });
+        }
+
+        let mut copy = self.builder(upload_queue)?;
+        let texture = Texture::record_upload(&self.gpu_resources, image, options, &mut copy)?;
+        let mut mipmaps = self.builder(graphics_queue)?;
+        texture.record_mipmaps(&mut mipmaps)?;
+
+        let upload = sync::now(self.gpu_resources.device().clone())
+            .then_execute(upload_queue.clone(), copy.build()?)?
+            .then_signal_semaphore()
+            .then_execute(graphics_queue.clone(), mipmaps.build()?)?
+            .then_signal_semaphore_and_flush()?;
+        self.submitted.lock().unwrap().push(upload.boxed_send_sync());
+
+        Ok(texture)
+    }
+
+    /// How many uploads are still to be waited on by a frame.
+    pub fn pending(&self) -> usize {
+        self.submitted.lock().unwrap().len()
+    }
+
+    /// Joins the uploads submitted since the last call into one future. The
+    /// next submission drawing with their resources has to wait on it.
+    pub fn take_submitted(&self) -> Option<Box<dyn GpuFuture>> {
+        let submitted = std::mem::take(&mut *self.submitted.lock().unwrap());
+
+        submitted
+            .into_iter()
+            .map(|upload| upload as Box<dyn GpuFuture>)
+            .reduce(|uploads, upload| uploads.join(upload).boxed())
+    }
+
+    fn submit<T>(
+        &self,
+        queue: &Arc<Queue>,
+        record: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<T, Errors>,
+    ) -> Result<T, Errors> {
+        let mut builder = self.builder(queue)?;
+        let result = record(&mut builder)?;
+
+        let upload = sync::now(self.gpu_resources.device().clone())
+            .then_execute(queue.clone(), builder.build()?)?
+            .then_signal_semaphore_and_flush()?;
+        self.submitted.lock().unwrap().push(upload.boxed_send_sync());
+
+        Ok(result)
+    }
+
+    fn builder(&self, queue: &Arc<Queue>) -> Result<AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, Errors> {
+        Ok(AutoCommandBufferBuilder::primary(
+            self.gpu_resources.command_buffer_allocator(),
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?)
+    }
+}
@@ -1,27 +1,70 @@
 use std::sync::Arc;
 
+use log::info;
 use vulkano::{
     VulkanLibrary,
-    instance:: {Instance, InstanceCreateInfo},
+    instance:: {debug::DebugUtilsMessenger, Instance, InstanceCreateInfo, InstanceExtensions},
 };
 
+use crate::errors::Errors;
+
+use super::debug::{self, DebugSupport};
+
 pub struct VulkanInstanse {
     pub instance: Arc<Instance>,
+    /// Forwards validation messages to the log while debugging is enabled.
+    _debug_messenger: Option<DebugUtilsMessenger>,
 }
 
 impl VulkanInstanse {
-    pub fn new() -> Arc<VulkanInstanse> {
-        let library = VulkanLibrary::new().expect("no local Vulkan library/DLL");
-        let required_extensions = vulkano_win::required_extensions(&library);
+    /// With `debug`, enables the validation layer and debug messages where
+    /// available, as does setting `LUMR_VULKAN_DEBUG`.
+    pub fn new(debug: bool) -> Result<Arc<VulkanInstanse>, Errors> {
+        let library = VulkanLibrary::new()?;
+        let required_extensions = InstanceExtensions {
+            // Lets the swapchain offer HDR color spaces.
+            ext_swapchain_colorspace: library.supported_extensions().ext_swapchain_colorspace,
+            ..vulkano_win::required_extensions(&library)
+        };
+
+        Self::create(library, required_extensions, debug)
+    }
+
+    /// Creates an instance without any surface extensions, for rendering with no window.
+    pub fn new_headless(debug: bool) -> Result<Arc<VulkanInstanse>, Errors> {
+        let library = VulkanLibrary::new()?;
+
+        Self::create(library, InstanceExtensions::empty(), debug)
+    }
+
+    fn create(
+        library: Arc<VulkanLibrary>,
+        mut enabled_extensions: InstanceExtensions,
+        debug: bool,
+    ) -> Result<Arc<VulkanInstanse>, Errors> {
+        let mut enabled_layers = Vec::new();
+        if debug || debug::debug_requested_by_env() {
+            let debug_support = DebugSupport::query(&library)?;
+            info!("Vulkan debugging enabled with layers {:?}", debug_support.layers);
+            enabled_layers = debug_support.layers;
+            enabled_extensions = enabled_extensions.union(&debug_support.extensions);
+        }
+
         let instance = Instance::new(
             library,
             InstanceCreateInfo {
-                enabled_extensions: required_extensions,
+                enabled_extensions,
+                enabled_layers,
                 ..Default::default()
             },
-        )
-        .expect("failed to create instance");
+        )?;
+
+        let debug_messenger = if instance.enabled_extensions().ext_debug_utils {
+            Some(debug::create_debug_messenger(&instance)?)
+        } else {
+            None
+        };
 
-        Arc::new(VulkanInstanse { instance })
+        Ok(Arc::new(VulkanInstanse { instance, _debug_messenger: debug_messenger }))
     }
 }
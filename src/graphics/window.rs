@@ -1,7 +1,7 @@
 use winit::{
-    event::{Event, WindowEvent},
+    event::{Event, StartCause, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::{WindowBuilder, Window}, dpi::PhysicalSize,
+    window::{WindowBuilder, Window}, dpi::{LogicalSize, PhysicalSize},
 };
 use vulkano::{
     instance::Instance,
@@ -10,6 +10,43 @@ use vulkano::{
 use vulkano_win::VkSurfaceBuild;
 use std::sync::Arc;
 
+use crate::errors::Errors;
+
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub resizable: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            title: "lumr".to_string(),
+            width: 800,
+            height: 600,
+            resizable: true,
+        }
+    }
+}
+
+/// What happened on one turn of the window's event loop.
+pub enum WindowLoopEvent<'a> {
+    /// The loop is about to process its first events.
+    Started,
+    /// An event for the window, forwarded as-is from winit.
+    Window(&'a WindowEvent<'a>),
+    /// All pending events were handled and a new frame should be produced.
+    Frame {
+        dimensions: PhysicalSize<u32>,
+        viewport: &'a Viewport,
+        window_resized: bool,
+    },
+    /// The loop is shutting down; no further events follow.
+    Exiting,
+}
+
 pub struct GraphicsWindow {
     pub window: Arc<Window>,
     pub surface: Arc<Surface>,
@@ -18,19 +55,20 @@ pub struct GraphicsWindow {
 }
 
 impl GraphicsWindow {
-    pub fn new(instance: Arc<Instance>) -> GraphicsWindow {
+    pub fn new(instance: Arc<Instance>, config: &WindowConfig) -> Result<GraphicsWindow, Errors> {
         let event_loop = EventLoop::new();
 
         let surface = WindowBuilder::new()
-            .build_vk_surface(&event_loop, instance)
-            .unwrap();
+            .with_title(config.title.clone())
+            .with_inner_size(LogicalSize::new(config.width, config.height))
+            .with_resizable(config.resizable)
+            .build_vk_surface(&event_loop, instance)?;
 
         let window = surface
             .object()
-            .unwrap()
-            .clone()
-            .downcast::<Window>()
-            .unwrap();
+            .cloned()
+            .and_then(|object| object.downcast::<Window>().ok())
+            .ok_or_else(|| Errors::Surface("surface is not backed by a winit window".to_string()))?;
 
         let viewport = Viewport {
             origin: [0.0, 0.0],
@@ -38,41 +76,55 @@ impl GraphicsWindow {
             depth_range: 0.0..1.0,
         };
 
-        GraphicsWindow { window, surface, event_loop, viewport }
+        Ok(GraphicsWindow { window, surface, event_loop, viewport })
     }
 
-    pub fn run_event_loop<F>(mut self, mut on_update: F)
+    /// Runs the event loop until the window is closed, the handler sets
+    /// `ControlFlow::Exit`, or the handler returns an error. The loop starts
+    /// out polling; the handler may switch it to waiting between frames.
+    pub fn run_event_loop<F>(mut self, mut handler: F)
     where
-        F: 'static + FnMut(PhysicalSize<u32>, &Viewport, bool),
+        F: 'static + FnMut(WindowLoopEvent, &mut ControlFlow) -> Result<(), Errors>,
     {
         let mut window_resized = false;
 
         self.event_loop.run(move |event, _, control_flow| {
-            *control_flow = ControlFlow::Wait;
-    
-            match event {
-                Event::WindowEvent {
-                    event: WindowEvent::CloseRequested,
-                    window_id,
-                } if window_id == self.window.id() => *control_flow = ControlFlow::Exit,
-                Event::WindowEvent {
-                    event: WindowEvent::Resized(_),
-                    ..
-                } => {
-                    window_resized = true;
+            let result = match event {
+                Event::NewEvents(StartCause::Init) => {
+                    *control_flow = ControlFlow::Poll;
+                    handler(WindowLoopEvent::Started, control_flow)
+                }
+                Event::WindowEvent { event, window_id } if window_id == self.window.id() => {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::Resized(_) => window_resized = true,
+                        _ => (),
+                    }
+
+                    handler(WindowLoopEvent::Window(&event), control_flow)
                 }
                 Event::MainEventsCleared => {
                     if window_resized {
                         self.viewport.dimensions = self.window.inner_size().into();
                     }
 
-                    on_update(self.window.inner_size(), &self.viewport, window_resized);
-
+                    let frame = WindowLoopEvent::Frame {
+                        dimensions: self.window.inner_size(),
+                        viewport: &self.viewport,
+                        window_resized,
+                    };
                     window_resized = false;
+
+                    handler(frame, control_flow)
                 }
-                 _ => (),
+                Event::LoopDestroyed => handler(WindowLoopEvent::Exiting, control_flow),
+                 _ => Ok(()),
+            };
+
+            if let Err(e) = result {
+                log::error!("stopping application: {e}");
+                *control_flow = ControlFlow::Exit;
             }
         });
     }
 }
-
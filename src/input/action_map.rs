@@ -0,0 +1,143 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::errors::Errors;
+
+use super::gamepad::{GamepadAxis, GamepadButton};
+
+/// A physical input that can trigger an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+/// Inputs that drive an axis: held `negative` bindings pull it towards -1,
+/// held `positive` ones towards 1, and `gamepad` adds an analog axis on top.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisBinding {
+    pub negative: Vec<Binding>,
+    pub positive: Vec<Binding>,
+    pub gamepad: Option<GamepadAxis>,
+}
+
+/// Named actions and axes mapped to the inputs that trigger them.
+///
+/// Maps are usually loaded from TOML:
+///
+/// ```toml
+/// [actions]
+/// jump = [{ key = "Space" }, { gamepad = "South" }]
+/// fire = [{ mouse = "Left" }]
+///
+/// [axes.move_x]
+/// negative = [{ key = "A" }]
+/// positive = [{ key = "D" }]
+/// gamepad = "LeftStickX"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionMap {
+    actions: HashMap<String, Vec<Binding>>,
+    axes: HashMap<String, AxisBinding>,
+}
+
+impl ActionMap {
+    pub fn new() -> ActionMap {
+        ActionMap::default()
+    }
+
+    pub fn from_toml(source: &str) -> Result<ActionMap, Errors> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<ActionMap, Errors> {
+        ActionMap::from_toml(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, Errors> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Writes the map back out, e.g. after the player rebinds controls.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Errors> {
+        Ok(fs::write(path, self.to_toml()?)?)
+    }
+
+    /// Adds `binding` to the action, keeping any existing ones.
+    pub fn bind_action(&mut self, action: impl Into<String>, binding: Binding) {
+        let bindings = self.actions.entry(action.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Replaces every binding of the action.
+    pub fn set_action(&mut self, action: impl Into<String>, bindings: Vec<Binding>) {
+        self.actions.insert(action.into(), bindings);
+    }
+
+    pub fn unbind_action(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    pub fn set_axis(&mut self, axis: impl Into<String>, binding: AxisBinding) {
+        self.axes.insert(axis.into(), binding);
+    }
+
+    pub fn action(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axis(&self, axis: &str) -> Option<&AxisBinding> {
+        self.axes.get(axis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_and_saves_toml() {
+        let action_map = ActionMap::from_toml(
+            r#"
+            [actions]
+            jump = [{ key = "Space" }, { gamepad = "South" }]
+            fire = [{ mouse = "Left" }]
+
+            [axes.move_x]
+            negative = [{ key = "A" }]
+            positive = [{ key = "D" }]
+            gamepad = "LeftStickX"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            action_map.action("jump"),
+            &[Binding::Key(VirtualKeyCode::Space), Binding::Gamepad(GamepadButton::South)]
+        );
+        assert_eq!(action_map.action("fire"), &[Binding::Mouse(MouseButton::Left)]);
+        assert_eq!(action_map.axis("move_x").unwrap().gamepad, Some(GamepadAxis::LeftStickX));
+        assert!(action_map.action("crouch").is_empty());
+
+        let reloaded = ActionMap::from_toml(&action_map.to_toml().unwrap()).unwrap();
+        assert_eq!(reloaded, action_map);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(matches!(
+            ActionMap::from_toml(r#"actions = { jump = [{ key = "NotAKey" }] }"#),
+            Err(Errors::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashSet, hash::Hash};
+
+/// Tracks which buttons are held and which changed since the last frame.
+#[derive(Debug)]
+pub struct ButtonState<T> {
+    held: HashSet<T>,
+    pressed: HashSet<T>,
+    released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> ButtonState<T> {
+    pub fn new() -> ButtonState<T> {
+        ButtonState { held: HashSet::new(), pressed: HashSet::new(), released: HashSet::new() }
+    }
+
+    pub fn press(&mut self, button: T) {
+        // Key repeat sends more presses while held; only the first one counts.
+        if self.held.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: T) {
+        if self.held.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    /// Releases everything, e.g. when the window loses focus.
+    pub fn release_all(&mut self) {
+        self.released.extend(self.held.drain());
+    }
+
+    /// True only in the frame the button went down.
+    pub fn pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn held(&self, button: T) -> bool {
+        self.held.contains(&button)
+    }
+
+    /// True only in the frame the button went up.
+    pub fn released(&self, button: T) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
+
+impl<T: Copy + Eq + Hash> Default for ButtonState<T> {
+    fn default() -> Self {
+        ButtonState::new()
+    }
+}
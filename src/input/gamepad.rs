@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/// Gamepad buttons, named by their position on the pad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftTrigger,
+    LeftTrigger2,
+    RightTrigger,
+    RightTrigger2,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftZ,
+    RightZ,
+}
+
+/// Gamepad input fed to `Input::handle_gamepad_event`.
+///
+/// winit does not report gamepads, so these come from whichever gamepad
+/// library the game uses. Events from all connected pads are merged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadEvent {
+    ButtonPressed(GamepadButton),
+    ButtonReleased(GamepadButton),
+    /// New axis value in `-1.0..=1.0`.
+    AxisChanged(GamepadAxis, f32),
+    /// Releases every button and recenters every axis.
+    Disconnected,
+}
@@ -0,0 +1,344 @@
+use std::{collections::HashMap, hash::Hash};
+
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent},
+};
+
+use super::{
+    action_map::{ActionMap, Binding},
+    button_state::ButtonState,
+    gamepad::{GamepadAxis, GamepadButton, GamepadEvent},
+};
+
+/// Pixel scroll deltas (touchpads) are converted to lines with this factor.
+const PIXELS_PER_SCROLL_LINE: f64 = 20.0;
+/// Gamepad axis values closer to zero than this are treated as zero.
+const GAMEPAD_DEAD_ZONE: f32 = 0.15;
+
+/// Keyboard, mouse and gamepad state for the current frame.
+///
+/// "Pressed" and "released" mean the change happened since the previous
+/// rendered frame; "held" is the current state.
+pub struct Input {
+    keys: ButtonState<VirtualKeyCode>,
+    mouse_buttons: ButtonState<MouseButton>,
+    gamepad_buttons: ButtonState<GamepadButton>,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    cursor_position: Option<PhysicalPosition<f64>>,
+    cursor_delta: (f64, f64),
+    scroll_delta: (f32, f32),
+    text: String,
+    action_map: ActionMap,
+}
+
+impl Input {
+    pub fn new(action_map: ActionMap) -> Input {
+        Input {
+            keys: ButtonState::new(),
+            mouse_buttons: ButtonState::new(),
+            gamepad_buttons: ButtonState::new(),
+            gamepad_axes: HashMap::new(),
+            cursor_position: None,
+            cursor_delta: (0.0, 0.0),
+            scroll_delta: (0.0, 0.0),
+            text: String::new(),
+            action_map,
+        }
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state, virtual_keycode: Some(key), .. },
+                ..
+            } => Self::update_button(&mut self.keys, *key, *state),
+            WindowEvent::MouseInput { state, button, .. } => {
+                Self::update_button(&mut self.mouse_buttons, *button, *state)
+            }
+            WindowEvent::CursorMoved { position, .. } => self.move_cursor(*position),
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(delta) => (
+                        (delta.x / PIXELS_PER_SCROLL_LINE) as f32,
+                        (delta.y / PIXELS_PER_SCROLL_LINE) as f32,
+                    ),
+                };
+                self.scroll_delta.0 += x;
+                self.scroll_delta.1 += y;
+            }
+            WindowEvent::ReceivedCharacter(character) if !character.is_control() => {
+                self.text.push(*character)
+            }
+            WindowEvent::Focused(false) => {
+                // Release events for keys let go while unfocused never arrive.
+                self.keys.release_all();
+                self.mouse_buttons.release_all();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn handle_gamepad_event(&mut self, event: GamepadEvent) {
+        match event {
+            GamepadEvent::ButtonPressed(button) => self.gamepad_buttons.press(button),
+            GamepadEvent::ButtonReleased(button) => self.gamepad_buttons.release(button),
+            GamepadEvent::AxisChanged(axis, value) => {
+                self.gamepad_axes.insert(axis, value.clamp(-1.0, 1.0));
+            }
+            GamepadEvent::Disconnected => {
+                self.gamepad_buttons.release_all();
+                self.gamepad_axes.clear();
+            }
+        }
+    }
+
+    /// Clears per-frame state; called by the application after each rendered frame.
+    pub fn end_frame(&mut self) {
+        self.keys.end_frame();
+        self.mouse_buttons.end_frame();
+        self.gamepad_buttons.end_frame();
+        self.cursor_delta = (0.0, 0.0);
+        self.scroll_delta = (0.0, 0.0);
+        self.text.clear();
+    }
+
+    pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys.pressed(key)
+    }
+
+    pub fn key_held(&self, key: VirtualKeyCode) -> bool {
+        self.keys.held(key)
+    }
+
+    pub fn key_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys.released(key)
+    }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.pressed(button)
+    }
+
+    pub fn mouse_held(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.held(button)
+    }
+
+    pub fn mouse_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.released(button)
+    }
+
+    pub fn gamepad_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons.pressed(button)
+    }
+
+    pub fn gamepad_held(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons.held(button)
+    }
+
+    pub fn gamepad_released(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons.released(button)
+    }
+
+    /// Axis value in `-1.0..=1.0` with the dead zone applied.
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        let value = self.gamepad_axes.get(&axis).copied().unwrap_or(0.0);
+        if value.abs() < GAMEPAD_DEAD_ZONE { 0.0 } else { value }
+    }
+
+    /// Cursor position in physical pixels, or `None` while it is outside the window.
+    pub fn cursor_position(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor_position
+    }
+
+    /// How far the cursor moved this frame, in physical pixels.
+    pub fn cursor_delta(&self) -> (f64, f64) {
+        self.cursor_delta
+    }
+
+    /// Scroll this frame in lines, positive `y` is away from the user.
+    pub fn scroll_delta(&self) -> (f32, f32) {
+        self.scroll_delta
+    }
+
+    /// Characters typed this frame.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn action_map(&self) -> &ActionMap {
+        &self.action_map
+    }
+
+    /// Lets the game rebind actions at runtime.
+    pub fn action_map_mut(&mut self) -> &mut ActionMap {
+        &mut self.action_map
+    }
+
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.action_map.action(action).iter().any(|binding| self.binding_pressed(*binding))
+    }
+
+    pub fn action_held(&self, action: &str) -> bool {
+        self.action_map.action(action).iter().any(|binding| self.binding_held(*binding))
+    }
+
+    /// True in the frame the last held binding of the action was let go.
+    pub fn action_released(&self, action: &str) -> bool {
+        let bindings = self.action_map.action(action);
+        bindings.iter().any(|binding| self.binding_released(*binding))
+            && !bindings.iter().any(|binding| self.binding_held(*binding))
+    }
+
+    /// Axis value in `-1.0..=1.0`; 0 for unknown axes.
+    pub fn axis(&self, axis: &str) -> f32 {
+        let Some(binding) = self.action_map.axis(axis) else {
+            return 0.0;
+        };
+
+        let held = |bindings: &[Binding]| bindings.iter().any(|binding| self.binding_held(*binding));
+        let mut value = held(&binding.positive) as i32 as f32 - held(&binding.negative) as i32 as f32;
+        if let Some(gamepad_axis) = binding.gamepad {
+            value += self.gamepad_axis(gamepad_axis);
+        }
+
+        value.clamp(-1.0, 1.0)
+    }
+
+    fn binding_pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keys.pressed(key),
+            Binding::Mouse(button) => self.mouse_buttons.pressed(button),
+            Binding::Gamepad(button) => self.gamepad_buttons.pressed(button),
+        }
+    }
+
+    fn binding_held(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keys.held(key),
+            Binding::Mouse(button) => self.mouse_buttons.held(button),
+            Binding::Gamepad(button) => self.gamepad_buttons.held(button),
+        }
+    }
+
+    fn binding_released(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keys.released(key),
+            Binding::Mouse(button) => self.mouse_buttons.released(button),
+            Binding::Gamepad(button) => self.gamepad_buttons.released(button),
+        }
+    }
+
+    fn update_button<T: Copy + Eq + Hash>(buttons: &mut ButtonState<T>, button: T, state: ElementState) {
+        match state {
+            ElementState::Pressed => buttons.press(button),
+            ElementState::Released => buttons.release(button),
+        }
+    }
+
+    fn move_cursor(&mut self, position: PhysicalPosition<f64>) {
+        // The first position after entering the window is not a movement.
+        if let Some(previous) = self.cursor_position {
+            self.cursor_delta.0 += position.x - previous.x;
+            self.cursor_delta.1 += position.y - previous.y;
+        }
+        self.cursor_position = Some(position);
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input::new(ActionMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::action_map::AxisBinding;
+
+    fn input() -> Input {
+        let mut action_map = ActionMap::new();
+        action_map.bind_action("jump", Binding::Key(VirtualKeyCode::Space));
+        action_map.bind_action("jump", Binding::Gamepad(GamepadButton::South));
+        action_map.set_axis(
+            "move_x",
+            AxisBinding {
+                negative: vec![Binding::Key(VirtualKeyCode::A)],
+                positive: vec![Binding::Key(VirtualKeyCode::D)],
+                gamepad: Some(GamepadAxis::LeftStickX),
+            },
+        );
+
+        Input::new(action_map)
+    }
+
+    #[test]
+    fn pressed_and_released_last_one_frame() {
+        let mut input = input();
+
+        Input::update_button(&mut input.keys, VirtualKeyCode::Space, ElementState::Pressed);
+        assert!(input.action_pressed("jump"));
+        assert!(input.action_held("jump"));
+
+        input.end_frame();
+        // Key repeat must not report a new press.
+        Input::update_button(&mut input.keys, VirtualKeyCode::Space, ElementState::Pressed);
+        assert!(!input.action_pressed("jump"));
+        assert!(input.action_held("jump"));
+
+        Input::update_button(&mut input.keys, VirtualKeyCode::Space, ElementState::Released);
+        assert!(input.action_released("jump"));
+        assert!(!input.action_held("jump"));
+
+        input.end_frame();
+        assert!(!input.action_released("jump"));
+    }
+
+    #[test]
+    fn action_released_waits_for_every_binding() {
+        let mut input = input();
+
+        Input::update_button(&mut input.keys, VirtualKeyCode::Space, ElementState::Pressed);
+        input.handle_gamepad_event(GamepadEvent::ButtonPressed(GamepadButton::South));
+        Input::update_button(&mut input.keys, VirtualKeyCode::Space, ElementState::Released);
+        assert!(!input.action_released("jump"));
+
+        input.handle_gamepad_event(GamepadEvent::ButtonReleased(GamepadButton::South));
+        assert!(input.action_released("jump"));
+    }
+
+    #[test]
+    fn axis_combines_keys_and_gamepad() {
+        let mut input = input();
+
+        Input::update_button(&mut input.keys, VirtualKeyCode::D, ElementState::Pressed);
+        assert_eq!(input.axis("move_x"), 1.0);
+
+        Input::update_button(&mut input.keys, VirtualKeyCode::A, ElementState::Pressed);
+        assert_eq!(input.axis("move_x"), 0.0);
+
+        input.handle_gamepad_event(GamepadEvent::AxisChanged(GamepadAxis::LeftStickX, 0.1));
+        assert_eq!(input.axis("move_x"), 0.0);
+        input.handle_gamepad_event(GamepadEvent::AxisChanged(GamepadAxis::LeftStickX, -0.5));
+        assert_eq!(input.axis("move_x"), -0.5);
+
+        assert_eq!(input.axis("unknown"), 0.0);
+    }
+
+    #[test]
+    fn cursor_delta_accumulates_per_frame() {
+        let mut input = input();
+
+        input.move_cursor(PhysicalPosition::new(10.0, 10.0));
+        assert_eq!(input.cursor_delta(), (0.0, 0.0));
+        input.move_cursor(PhysicalPosition::new(15.0, 8.0));
+        input.move_cursor(PhysicalPosition::new(20.0, 4.0));
+        assert_eq!(input.cursor_delta(), (10.0, -6.0));
+
+        input.end_frame();
+        assert_eq!(input.cursor_delta(), (0.0, 0.0));
+        assert_eq!(input.cursor_position(), Some(PhysicalPosition::new(20.0, 4.0)));
+    }
+}
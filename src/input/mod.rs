@@ -0,0 +1,4 @@
+pub mod action_map;
+pub mod button_state;
+pub mod gamepad;
+pub mod input_state;
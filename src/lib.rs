@@ -2,6 +2,7 @@ pub mod errors;
 pub mod logger;
 pub mod graphics;
 pub mod core;
+pub mod input;
 
 pub fn add(left: usize, right: usize) -> usize {
     left + right
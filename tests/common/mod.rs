@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use image::{Rgba, RgbaImage};
+use lumr::{core::headless_application::HeadlessApplication, errors::Errors};
+
+/// Set to regenerate the reference images from the current renderer output.
+const BLESS_ENV: &str = "LUMR_BLESS_GOLDEN";
+/// Set on machines that must run the GPU tests, e.g. GPU CI runners, to fail
+/// instead of skipping when no Vulkan device is found.
+const REQUIRE_GPU_ENV: &str = "LUMR_REQUIRE_GPU";
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// Largest per-channel difference still counted as a match.
+    pub per_channel: u8,
+    /// Number of pixels allowed to exceed `per_channel`, to absorb
+    /// rasterization differences along edges between drivers.
+    pub max_mismatched_pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance { per_channel: 2, max_mismatched_pixels: 16 }
+    }
+}
+
+pub struct Comparison {
+    pub mismatched_pixels: usize,
+    pub diff: RgbaImage,
+}
+
+/// Creates a headless application, or returns `None` when the machine has no
+/// Vulkan implementation so GPU tests are skipped instead of failing, unless
+/// `LUMR_REQUIRE_GPU` is set.
+pub fn headless_application(width: u32, height: u32) -> Option<HeadlessApplication> {
+    match HeadlessApplication::new(width, height) {
+        Ok(application) => Some(application),
+        Err(e @ (Errors::VulkanLibraryMissing(_) | Errors::NoSuitableDevice(_))) => {
+            if std::env::var_os(REQUIRE_GPU_ENV).is_some() {
+                panic!("no Vulkan device for golden image test ({REQUIRE_GPU_ENV} is set): {e}");
+            }
+            eprintln!("skipping golden image test: {e}");
+            None
+        }
+        Err(e) => panic!("failed to create headless application: {e}"),
+    }
+}
+
+/// Compares two images pixel by pixel. The diff image shows the reference in
+/// dimmed grayscale with mismatching pixels highlighted in magenta.
+pub fn compare(reference: &RgbaImage, actual: &RgbaImage, tolerance: Tolerance) -> Comparison {
+    assert_eq!(reference.dimensions(), actual.dimensions(), "image dimensions differ");
+
+    let mut mismatched_pixels = 0;
+    let mut diff = RgbaImage::new(reference.width(), reference.height());
+
+    for ((x, y, expected), found) in reference.enumerate_pixels().zip(actual.pixels()) {
+        let matches = expected
+            .0
+            .iter()
+            .zip(found.0.iter())
+            .all(|(e, f)| e.abs_diff(*f) <= tolerance.per_channel);
+
+        let pixel = if matches {
+            let [r, g, b, _] = expected.0;
+            let luma = ((r as u32 + g as u32 + b as u32) / 3 / 4) as u8;
+            Rgba([luma, luma, luma, 255])
+        } else {
+            mismatched_pixels += 1;
+            Rgba([255, 0, 255, 255])
+        };
+        diff.put_pixel(x, y, pixel);
+    }
+
+    Comparison { mismatched_pixels, diff }
+}
+
+/// Checks `rgba` against `tests/golden/<name>.png`. On failure the actual
+/// frame and a diff image are written to `target/golden/` for inspection.
+pub fn assert_matches_golden(name: &str, width: u32, height: u32, rgba: Vec<u8>, tolerance: Tolerance) {
+    let actual = RgbaImage::from_raw(width, height, rgba).expect("frame size does not match dimensions");
+    let reference_path = manifest_dir().join("tests").join("golden").join(format!("{name}.png"));
+
+    if std::env::var_os(BLESS_ENV).is_some() {
+        actual.save(&reference_path).unwrap();
+        eprintln!("blessed {}", reference_path.display());
+        return;
+    }
+
+    let reference = image::open(&reference_path)
+        .unwrap_or_else(|e| panic!("failed to open {}: {e} (run with {BLESS_ENV}=1 to create it)", reference_path.display()))
+        .to_rgba8();
+
+    let comparison = compare(&reference, &actual, tolerance);
+    if comparison.mismatched_pixels <= tolerance.max_mismatched_pixels {
+        return;
+    }
+
+    let output_dir = manifest_dir().join("target").join("golden");
+    std::fs::create_dir_all(&output_dir).unwrap();
+    let actual_path = output_dir.join(format!("{name}.actual.png"));
+    let diff_path = output_dir.join(format!("{name}.diff.png"));
+    actual.save(&actual_path).unwrap();
+    comparison.diff.save(&diff_path).unwrap();
+
+    panic!(
+        "golden image `{name}` differs in {} pixels (allowed {}); see {} and {}",
+        comparison.mismatched_pixels,
+        tolerance.max_mismatched_pixels,
+        actual_path.display(),
+        diff_path.display(),
+    );
+}
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
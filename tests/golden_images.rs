@@ -0,0 +1,494 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::Tolerance;
+use image::{Rgba, RgbaImage};
+use glam::{Mat4, Vec3};
+use lumr::core::headless_application::HeadlessApplication;
+use lumr::errors::Errors;
+use lumr::graphics::{
+    camera::Camera, compute::{ComputePass, ComputeStage}, depth::DepthConfig, draw_list::ScreenRect,
+    material::{BlendMode, StandardParameters}, mesh::{MeshData, MeshVertex}, shader::{Shader, ShaderStage},
+    texture::{SamplerConfig, Texture, TextureOptions},
+};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage};
+
+fn triangle() -> MeshData {
+    let red = [1.0, 0.0, 0.0, 1.0];
+    MeshData::new(
+        vec![
+            MeshVertex::new([-0.5, -0.5, 0.0]).with_color(red),
+            MeshVertex::new([0.0, 0.5, 0.0]).with_color(red),
+            MeshVertex::new([0.5, -0.25, 0.0]).with_color(red),
+        ],
+        None,
+    )
+}
+
+#[test]
+fn triangle_matches_golden() {
+    let Some(mut application) = common::headless_application(128, 128) else {
+        return;
+    };
+    let triangle = application.upload_mesh(&triangle()).unwrap();
+    application.draw_list_mut().draw(&triangle);
+
+    let [width, height] = application.dimensions();
+    let frame = application.render_frame().unwrap();
+
+    common::assert_matches_golden("triangle", width, height, frame, Tolerance::default());
+}
+
+#[test]
+fn indexed_meshes_draw_in_order() {
+    let Some(mut application) = common::headless_application(64, 64) else {
+        return;
+    };
+
+    let mut quad = MeshData::quad();
+    quad.vertices.iter_mut().for_each(|vertex| vertex.color = [0.0, 1.0, 0.0, 1.0]);
+    let white = [1.0, 1.0, 1.0, 1.0];
+    let corner = MeshData::new(
+        vec![
+            MeshVertex::new([0.0, 0.0, 0.0]).with_color(white),
+            MeshVertex::new([0.5, 0.0, 0.0]).with_color(white),
+            MeshVertex::new([0.0, 0.5, 0.0]).with_color(white),
+        ],
+        None,
+    );
+    let quad = application.upload_mesh(&quad).unwrap();
+    let corner = application.upload_mesh(&corner).unwrap();
+    application.draw_list_mut().draw(&quad);
+    application.draw_list_mut().draw(&corner);
+
+    let frame = RgbaImage::from_raw(64, 64, application.render_frame().unwrap()).unwrap();
+
+    assert_eq!(*frame.get_pixel(2, 2), Rgba([0, 0, 255, 255]));
+    assert_eq!(*frame.get_pixel(24, 24), Rgba([0, 255, 0, 255]));
+    assert_eq!(*frame.get_pixel(36, 36), Rgba([255, 255, 255, 255]));
+}
+
+#[test]
+fn draw_list_changes_between_frames() {
+    let Some(mut application) = common::headless_application(32, 32) else {
+        return;
+    };
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+
+    application.draw_list_mut().draw(&quad);
+    let frame = RgbaImage::from_raw(32, 32, application.render_frame().unwrap()).unwrap();
+    assert_eq!(*frame.get_pixel(16, 16), Rgba([255, 255, 255, 255]));
+
+    application.draw_list_mut().clear();
+    let frame = RgbaImage::from_raw(32, 32, application.render_frame().unwrap()).unwrap();
+    assert_eq!(*frame.get_pixel(16, 16), Rgba([0, 0, 255, 255]));
+}
+
+#[test]
+fn camera_and_transform_place_meshes() {
+    let Some(mut application) = common::headless_application(64, 64) else {
+        return;
+    };
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+
+    // World +Y is up, so a quad moved up and right lands in the top-right quarter.
+    let draw_list = application.draw_list_mut();
+    draw_list.set_camera(Some(Camera::orthographic(2.0, 0.0, 10.0).with_position(Vec3::new(0.0, 0.0, 1.0))));
+    draw_list.draw_with_transform(&quad, Mat4::from_translation(Vec3::new(0.5, 0.5, 0.0)) * Mat4::from_scale(Vec3::splat(0.5)));
+
+    let frame = RgbaImage::from_raw(64, 64, application.render_frame().unwrap()).unwrap();
+
+    assert_eq!(*frame.get_pixel(48, 16), Rgba([255, 255, 255, 255]));
+    assert_eq!(*frame.get_pixel(48, 48), Rgba([0, 0, 255, 255]));
+    assert_eq!(*frame.get_pixel(32, 32), Rgba([0, 0, 255, 255]));
+}
+
+#[test]
+fn depth_test_hides_farther_meshes() {
+    let Some(mut application) = common::headless_application(32, 32) else {
+        return;
+    };
+    let mut near = MeshData::quad();
+    near.vertices.iter_mut().for_each(|vertex| vertex.color = [0.0, 1.0, 0.0, 1.0]);
+    let near = application.upload_mesh(&near).unwrap();
+    let far = application.upload_mesh(&MeshData::quad()).unwrap();
+
+    let draw_list = application.draw_list_mut();
+    draw_list.set_camera(Some(Camera::orthographic(2.0, 0.1, 10.0).with_position(Vec3::new(0.0, 0.0, 2.0))));
+    draw_list.draw_with_transform(&near, Mat4::from_translation(Vec3::new(0.0, 0.0, 0.5)));
+    draw_list.draw_with_transform(&far, Mat4::from_translation(Vec3::new(0.0, 0.0, -0.5)));
+
+    let frame = RgbaImage::from_raw(32, 32, application.render_frame().unwrap()).unwrap();
+
+    assert_eq!(*frame.get_pixel(16, 16), Rgba([0, 255, 0, 255]));
+}
+
+#[test]
+fn textured_quad_samples_texels() {
+    let Some(mut application) = common::headless_application(32, 32) else {
+        return;
+    };
+    let mut texels = RgbaImage::new(2, 2);
+    texels.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+    texels.put_pixel(1, 0, Rgba([0, 255, 0, 255]));
+    texels.put_pixel(0, 1, Rgba([255, 255, 255, 255]));
+    texels.put_pixel(1, 1, Rgba([0, 0, 0, 255]));
+    let options = TextureOptions { srgb: false, mipmaps: false, sampler: SamplerConfig::nearest() };
+    let texture = application.upload_texture(&texels, &options).unwrap();
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+
+    let material = application.textured_material_builder(&texture).build().unwrap();
+
+    application.draw_list_mut().draw_with_material(&quad, Mat4::from_scale(Vec3::splat(2.0)), &material);
+    let frame = RgbaImage::from_raw(32, 32, application.render_frame().unwrap()).unwrap();
+
+    assert_eq!(*frame.get_pixel(8, 8), Rgba([255, 0, 0, 255]));
+    assert_eq!(*frame.get_pixel(24, 8), Rgba([0, 255, 0, 255]));
+    assert_eq!(*frame.get_pixel(8, 24), Rgba([255, 255, 255, 255]));
+    assert_eq!(*frame.get_pixel(24, 24), Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn mipmapped_texture_uploads_record_on_the_transfer_family() {
+    let Some(application) = common::headless_application(16, 16) else {
+        return;
+    };
+    let gpu_resources = application.renderer().gpu_resources();
+    let Some(transfer_queue) = &gpu_resources.device_manager().transfer_queue else {
+        return;
+    };
+    let mut builder = AutoCommandBufferBuilder::primary(
+        gpu_resources.command_buffer_allocator(),
+        transfer_queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+
+    let texture = Texture::record_upload(gpu_resources, &RgbaImage::new(64, 64), &TextureOptions::default(), &mut builder)
+        .unwrap();
+
+    assert_eq!(texture.mip_levels(), 7);
+    builder.build().unwrap();
+}
+
+#[test]
+fn streamed_mipmapped_textures_sample_their_smaller_levels() {
+    let Some(mut application) = common::headless_application(16, 16) else {
+        return;
+    };
+    let texels = RgbaImage::from_pixel(64, 64, Rgba([0, 255, 0, 255]));
+    let options = TextureOptions { srgb: false, ..TextureOptions::default() };
+    let texture = application.upload_texture(&texels, &options).unwrap();
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+
+    let material = application.textured_material_builder(&texture).build().unwrap();
+
+    // 64 texels across 16 pixels samples the third mip level.
+    application.draw_list_mut().draw_with_material(&quad, Mat4::from_scale(Vec3::splat(2.0)), &material);
+    let frame = RgbaImage::from_raw(16, 16, application.render_frame().unwrap()).unwrap();
+
+    assert_eq!(*frame.get_pixel(8, 8), Rgba([0, 255, 0, 255]));
+}
+
+#[test]
+fn materials_blend_and_share_pipelines() {
+    let Some(mut application) = common::headless_application(32, 32) else {
+        return;
+    };
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+    let green = application
+        .material_builder()
+        .with_parameters(StandardParameters { base_color: [0.0, 1.0, 0.0, 1.0] })
+        .with_blend(BlendMode::Additive)
+        .with_depth(DepthConfig::disabled())
+        .build()
+        .unwrap();
+    let also_green = application
+        .material_builder()
+        .with_parameters(StandardParameters { base_color: [0.0, 1.0, 0.0, 1.0] })
+        .with_blend(BlendMode::Additive)
+        .with_depth(DepthConfig::disabled())
+        .build()
+        .unwrap();
+
+    let left = Mat4::from_translation(Vec3::new(-0.5, 0.0, 0.0));
+    let right = Mat4::from_translation(Vec3::new(0.5, 0.0, 0.0));
+    application.draw_list_mut().draw_with_material(&quad, left, &green);
+    application.draw_list_mut().draw_with_material(&quad, right, &also_green);
+    let frame = RgbaImage::from_raw(32, 32, application.render_frame().unwrap()).unwrap();
+
+    // Additive green over the blue clear color.
+    assert_eq!(*frame.get_pixel(4, 16), Rgba([0, 255, 255, 255]));
+    assert_eq!(*frame.get_pixel(28, 16), Rgba([0, 255, 255, 255]));
+    assert_eq!(application.renderer().pipeline_cache().len(), 1);
+}
+
+#[test]
+fn changed_shader_files_are_reloaded() {
+    let Some(mut application) = common::headless_application(16, 16) else {
+        return;
+    };
+    let directory = std::env::temp_dir().join(format!("lumr-shader-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("solid.frag");
+    let solid = |color: &str| {
+        format!(
+            "#version 460\nlayout(location = 0) in vec4 v_color;\nlayout(location = 0) out vec4 f_color;\n\
+             void main() {{ f_color = vec4({color}); }}\n"
+        )
+    };
+    let write_shader = |source: &str, age: u64| {
+        std::fs::write(&path, source).unwrap();
+        // Explicit timestamps, so quick rewrites are seen even on coarse file systems.
+        let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(age);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+    };
+
+    write_shader(&solid("0.0, 1.0, 0.0, 1.0"), 20);
+    let fragment_shader = application.load_shader(&path, ShaderStage::Fragment).unwrap();
+    let vertex_shader = application.renderer().standard_shaders().vertex.clone();
+    let material = application.material_builder_with_shaders(vertex_shader, fragment_shader).build().unwrap();
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+    application.draw_list_mut().draw_with_material(&quad, Mat4::from_scale(Vec3::splat(2.0)), &material);
+    let pixel = |application: &mut HeadlessApplication| {
+        let frame = RgbaImage::from_raw(16, 16, application.render_frame().unwrap()).unwrap();
+        *frame.get_pixel(8, 8)
+    };
+    assert_eq!(pixel(&mut application), Rgba([0, 255, 0, 255]));
+
+    write_shader(&solid("1.0, 0.0, 0.0, 1.0"), 10);
+    application.reload_changed_shaders();
+    assert_eq!(pixel(&mut application), Rgba([255, 0, 0, 255]));
+
+    // A broken file keeps the last version that compiled.
+    write_shader("#version 460\nvoid main() { not glsl }\n", 5);
+    application.reload_changed_shaders();
+    assert_eq!(pixel(&mut application), Rgba([255, 0, 0, 255]));
+
+    // So does one that compiles but reads an input the vertex shader doesn't write.
+    write_shader(
+        "#version 460\nlayout(location = 4) in vec4 v_missing;\nlayout(location = 0) out vec4 f_color;\n\
+         void main() { f_color = v_missing; }\n",
+        0,
+    );
+    application.reload_changed_shaders();
+    assert_eq!(pixel(&mut application), Rgba([255, 0, 0, 255]));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn accepted_shader_reloads_are_not_reverted_by_later_failures() {
+    let Some(mut application) = common::headless_application(16, 16) else {
+        return;
+    };
+    let directory = std::env::temp_dir().join(format!("lumr-shader-accept-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let fragment_path = directory.join("solid.frag");
+    let write_fragment_shader = |color: &str, age: u64| {
+        let source = format!(
+            "#version 460\nlayout(location = 0) in vec4 v_color;\nlayout(location = 0) out vec4 f_color;\n\
+             void main() {{ f_color = v_color * vec4({color}); }}\n"
+        );
+        std::fs::write(&fragment_path, source).unwrap();
+        let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(age);
+        std::fs::File::options().write(true).open(&fragment_path).unwrap().set_modified(modified).unwrap();
+    };
+    // Doesn't write the `v_color` the fragment shader reads, so they don't link.
+    let vertex_path = directory.join("colorless.vert");
+    std::fs::write(
+        &vertex_path,
+        "#version 460\nlayout(location = 0) in vec3 position;\n\
+         layout(set = 0, binding = 0) uniform Camera { mat4 view; mat4 projection; mat4 view_projection; vec4 position; } camera;\n\
+         layout(push_constant) uniform Object { mat4 model; } object;\n\
+         void main() { gl_Position = camera.view_projection * object.model * vec4(position, 1.0); }\n",
+    )
+    .unwrap();
+
+    write_fragment_shader("0.0, 1.0, 0.0, 1.0", 20);
+    let fragment_shader = application.load_shader(&fragment_path, ShaderStage::Fragment).unwrap();
+    let standard_vertex_shader = application.renderer().standard_shaders().vertex.clone();
+    let colorless_vertex_shader = application.load_shader(&vertex_path, ShaderStage::Vertex).unwrap();
+    let material =
+        application.material_builder_with_shaders(standard_vertex_shader, fragment_shader.clone()).build().unwrap();
+    let mismatched = application.material_builder_with_shaders(colorless_vertex_shader, fragment_shader).build().unwrap();
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+    let fullscreen = Mat4::from_scale(Vec3::splat(2.0));
+    let draw = |application: &mut HeadlessApplication, material| {
+        let draw_list = application.draw_list_mut();
+        draw_list.clear();
+        draw_list.draw_with_material(&quad, fullscreen, material);
+        application.render_frame()
+    };
+    let pixel = |frame: Vec<u8>| *RgbaImage::from_raw(16, 16, frame).unwrap().get_pixel(8, 8);
+
+    write_fragment_shader("1.0, 0.0, 0.0, 1.0", 10);
+    application.reload_changed_shaders();
+    assert_eq!(pixel(draw(&mut application, &material).unwrap()), Rgba([255, 0, 0, 255]));
+
+    // The reload already drew, so a new pairing that can't link must not undo it.
+    assert!(draw(&mut application, &mismatched).is_err());
+    assert_eq!(pixel(draw(&mut application, &material).unwrap()), Rgba([255, 0, 0, 255]));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn materials_without_camera_or_object_block_fail_to_draw() {
+    let Some(mut application) = common::headless_application(16, 16) else {
+        return;
+    };
+    let directory = std::env::temp_dir().join(format!("lumr-material-layout-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("bare.vert");
+    std::fs::write(
+        &path,
+        "#version 460\nlayout(location = 0) in vec3 position;\nlayout(location = 0) out vec4 v_color;\n\
+         void main() { gl_Position = vec4(position, 1.0); v_color = vec4(1.0); }\n",
+    )
+    .unwrap();
+
+    let vertex_shader = application.load_shader(&path, ShaderStage::Vertex).unwrap();
+    let fragment_shader = application.renderer().standard_shaders().color.clone();
+    let material = application.material_builder_with_shaders(vertex_shader, fragment_shader).build().unwrap();
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+    application.draw_list_mut().draw_with_material(&quad, Mat4::IDENTITY, &material);
+
+    assert!(matches!(application.render_frame(), Err(Errors::ShaderLoad(_))));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn viewport_and_scissor_limit_draws() {
+    let Some(mut application) = common::headless_application(32, 32) else {
+        return;
+    };
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+    let fullscreen = Mat4::from_scale(Vec3::splat(2.0));
+
+    let draw_list = application.draw_list_mut();
+    // Left half through a viewport, top right quarter through a scissor rect.
+    draw_list.set_viewport(Some(ScreenRect::new(0, 0, 16, 32)));
+    draw_list.draw_with_transform(&quad, fullscreen);
+    draw_list.set_viewport(None);
+    draw_list.set_scissor(Some(ScreenRect::new(16, 0, 16, 16)));
+    draw_list.draw_with_transform(&quad, fullscreen);
+    let frame = RgbaImage::from_raw(32, 32, application.render_frame().unwrap()).unwrap();
+
+    assert_eq!(*frame.get_pixel(4, 28), Rgba([255, 255, 255, 255]));
+    assert_eq!(*frame.get_pixel(28, 4), Rgba([255, 255, 255, 255]));
+    assert_eq!(*frame.get_pixel(28, 28), Rgba([0, 0, 255, 255]));
+}
+
+/// Loads a compute shader that sets the color of every vertex in the storage
+/// buffer at binding 0 to the pushed color.
+fn load_recolor_shader(application: &HeadlessApplication, name: &str) -> (Arc<Shader>, std::path::PathBuf) {
+    let directory = std::env::temp_dir().join(format!("lumr-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("recolor.comp");
+    // Vertices are 16 floats with the color at 8, see `MeshVertex`.
+    std::fs::write(
+        &path,
+        "#version 460\nlayout(local_size_x = 64) in;\n\
+         layout(set = 0, binding = 0) buffer Vertices { float data[]; } vertices;\n\
+         layout(push_constant) uniform Recolor { vec4 color; } recolor;\n\
+         void main() {\n\
+             uint vertex = gl_GlobalInvocationID.x;\n\
+             if (vertex >= vertices.data.length() / 16) { return; }\n\
+             for (uint i = 0; i < 4; i++) { vertices.data[vertex * 16 + 8 + i] = recolor.color[i]; }\n\
+         }\n",
+    )
+    .unwrap();
+
+    (application.load_shader(&path, ShaderStage::Compute).unwrap(), directory)
+}
+
+#[test]
+fn compute_before_graphics_feeds_the_frame() {
+    let Some(mut application) = common::headless_application(16, 16) else {
+        return;
+    };
+    let (shader, directory) = load_recolor_shader(&application, "compute-before");
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+    let recolor = ComputePass::new(&shader)
+        .with_stage(ComputeStage::BeforeGraphics)
+        .with_threads([quad.vertex_count(), 1, 1], [64, 1, 1])
+        .with_buffer(0, quad.vertex_buffer().clone())
+        .with_push_constants([1.0f32, 0.0, 0.0, 1.0]);
+
+    let draw_list = application.draw_list_mut();
+    draw_list.dispatch(&recolor);
+    draw_list.draw_with_transform(&quad, Mat4::from_scale(Vec3::splat(2.0)));
+    let frame = RgbaImage::from_raw(16, 16, application.render_frame().unwrap()).unwrap();
+
+    assert_eq!(*frame.get_pixel(8, 8), Rgba([255, 0, 0, 255]));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn async_compute_finishes_before_the_frame_draws() {
+    let Some(mut application) = common::headless_application(16, 16) else {
+        return;
+    };
+    let (shader, directory) = load_recolor_shader(&application, "compute-async");
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+    let recolor = ComputePass::new(&shader)
+        .with_stage(ComputeStage::Async)
+        .with_threads([quad.vertex_count(), 1, 1], [64, 1, 1])
+        .with_buffer(0, quad.vertex_buffer().clone())
+        .with_push_constants([1.0f32, 1.0, 0.0, 1.0]);
+
+    let draw_list = application.draw_list_mut();
+    draw_list.dispatch(&recolor);
+    draw_list.draw_with_transform(&quad, Mat4::from_scale(Vec3::splat(2.0)));
+    let frame = RgbaImage::from_raw(16, 16, application.render_frame().unwrap()).unwrap();
+
+    assert_eq!(*frame.get_pixel(8, 8), Rgba([255, 255, 0, 255]));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn compute_after_graphics_runs_once_the_frame_is_drawn() {
+    let Some(mut application) = common::headless_application(16, 16) else {
+        return;
+    };
+    let (shader, directory) = load_recolor_shader(&application, "compute-after");
+    let quad = application.upload_mesh(&MeshData::quad()).unwrap();
+    let recolor = ComputePass::new(&shader)
+        .with_stage(ComputeStage::AfterGraphics)
+        .with_threads([quad.vertex_count(), 1, 1], [64, 1, 1])
+        .with_buffer(0, quad.vertex_buffer().clone())
+        .with_push_constants([0.0f32, 1.0, 0.0, 1.0]);
+    let fullscreen = Mat4::from_scale(Vec3::splat(2.0));
+    let pixel = |application: &mut HeadlessApplication| {
+        let frame = RgbaImage::from_raw(16, 16, application.render_frame().unwrap()).unwrap();
+        *frame.get_pixel(8, 8)
+    };
+
+    // Dispatched after drawing, the recolor only shows up in the next frame.
+    let draw_list = application.draw_list_mut();
+    draw_list.dispatch(&recolor);
+    draw_list.draw_with_transform(&quad, fullscreen);
+    assert_eq!(pixel(&mut application), Rgba([255, 255, 255, 255]));
+
+    let draw_list = application.draw_list_mut();
+    draw_list.clear();
+    draw_list.draw_with_transform(&quad, fullscreen);
+    assert_eq!(pixel(&mut application), Rgba([0, 255, 0, 255]));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn comparison_counts_pixels_outside_tolerance() {
+    let reference = RgbaImage::from_pixel(4, 4, Rgba([10, 20, 30, 255]));
+    let mut actual = reference.clone();
+    actual.put_pixel(0, 0, Rgba([12, 20, 30, 255]));
+    actual.put_pixel(3, 3, Rgba([10, 20, 90, 255]));
+
+    let comparison = common::compare(&reference, &actual, Tolerance { per_channel: 2, max_mismatched_pixels: 0 });
+
+    assert_eq!(comparison.mismatched_pixels, 1);
+    assert_eq!(*comparison.diff.get_pixel(3, 3), Rgba([255, 0, 255, 255]));
+}